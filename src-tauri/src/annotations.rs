@@ -0,0 +1,240 @@
+//! Tags and metadata stored in an entry's inline comment.
+//!
+//! Annotations follow the human-readable part of the comment as
+//! `@key=value` pairs, e.g.
+//!
+//! ```text
+//! 10.0.0.5  api.local  # staging API @tags=dev,api @by=alice @created=2025-03-01
+//! ```
+//!
+//! Values containing spaces or quotes are written in double quotes with
+//! `\"` and `\\` escapes; line breaks, tabs and other control characters
+//! are escaped too (`\n`, `\r`, `\t`, `\u{7f}`), so a value can never end
+//! the line it is written on. Only a trailing run of known keys is read as
+//! annotations; anything else stays part of the comment.
+
+use crate::models::EntryMeta;
+
+const TAGS: &str = "tags";
+const AUTHOR: &str = "by";
+const CREATED: &str = "created";
+const MODIFIED: &str = "modified";
+const LINK: &str = "link";
+const NOTE: &str = "note";
+
+const KEYS: &[&str] = &[TAGS, AUTHOR, CREATED, MODIFIED, LINK, NOTE];
+
+/// An inline comment split into its text, tags and metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Annotated {
+    pub comment: Option<String>,
+    pub tags: Vec<String>,
+    pub meta: EntryMeta,
+}
+
+/// Split the trailing annotations off an inline comment
+pub fn parse(text: Option<&str>) -> Annotated {
+    let Some(text) = text else {
+        return Annotated::default();
+    };
+
+    // The earliest `@` from which the rest of the comment is all annotations
+    let starts = text
+        .char_indices()
+        .filter(|&(i, c)| c == '@' && (i == 0 || text[..i].ends_with(char::is_whitespace)))
+        .map(|(i, _)| i);
+    for start in starts {
+        let Some(pairs) = parse_pairs(&text[start..]) else {
+            continue;
+        };
+
+        let mut annotated = Annotated {
+            comment: Some(text[..start].trim_end().to_string()).filter(|c| !c.is_empty()),
+            ..Annotated::default()
+        };
+        for (key, value) in pairs {
+            let meta = &mut annotated.meta;
+            match key {
+                TAGS => {
+                    annotated.tags = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|t| !t.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                }
+                AUTHOR => meta.author = Some(value),
+                CREATED => meta.created = Some(value),
+                MODIFIED => meta.modified = Some(value),
+                LINK => meta.link = Some(value),
+                _ => meta.note = Some(value),
+            }
+        }
+        return annotated;
+    }
+
+    Annotated {
+        comment: Some(text.to_string()),
+        ..Annotated::default()
+    }
+}
+
+/// Render comment text followed by the annotations, or `None` when there
+/// is nothing to write
+pub fn render(comment: Option<&str>, tags: &[String], meta: &EntryMeta) -> Option<String> {
+    let mut out = comment.unwrap_or("").trim_end().to_string();
+
+    let tags = tags.join(",");
+    let pairs = [
+        (TAGS, Some(tags.as_str()).filter(|t| !t.is_empty())),
+        (AUTHOR, meta.author.as_deref()),
+        (CREATED, meta.created.as_deref()),
+        (MODIFIED, meta.modified.as_deref()),
+        (LINK, meta.link.as_deref()),
+        (NOTE, meta.note.as_deref()),
+    ];
+
+    for (key, value) in pairs {
+        let Some(value) = value else {
+            continue;
+        };
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push('@');
+        out.push_str(key);
+        out.push('=');
+        out.push_str(&quote(value));
+    }
+
+    Some(out).filter(|o| !o.is_empty())
+}
+
+/// Read `@key=value` pairs separated by whitespace. Fails unless the whole
+/// text consists of pairs with known keys.
+fn parse_pairs(text: &str) -> Option<Vec<(&'static str, String)>> {
+    let mut pairs = Vec::new();
+    let mut rest = text.trim();
+
+    while !rest.is_empty() {
+        let (key, after) = rest.strip_prefix('@')?.split_once('=')?;
+        let key = KEYS.iter().find(|k| **k == key)?;
+
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => unquote(quoted)?,
+            None => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (after[..end].to_string(), &after[end..])
+            }
+        };
+
+        // A pair must end at whitespace or the end of the comment
+        if !after.is_empty() && !after.starts_with(char::is_whitespace) {
+            return None;
+        }
+
+        pairs.push((*key, value));
+        rest = after.trim_start();
+    }
+
+    Some(pairs)
+}
+
+/// Read a quoted value up to its closing quote, returning the value and
+/// the text after it
+fn unquote(text: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = text.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                'u' => {
+                    let rest = chars.as_str().strip_prefix('{')?;
+                    let (hex, _) = rest.split_once('}')?;
+                    value.push(char::from_u32(u32::from_str_radix(hex, 16).ok()?)?);
+                    // Skip past the closing brace
+                    for _ in 0..hex.len() + 2 {
+                        chars.next();
+                    }
+                }
+                escaped => value.push(escaped),
+            },
+            _ => value.push(c),
+        }
+    }
+
+    None
+}
+
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value.contains(|c: char| c.is_whitespace() || c.is_control() || c == '"' || c == '\\');
+    if plain {
+        return value.to_string();
+    }
+
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::HostsDocument;
+
+    const INJECTED: &str = "x\n0.0.0.0 bank.example";
+
+    #[test]
+    fn control_characters_are_escaped_and_read_back() {
+        let meta = EntryMeta {
+            note: Some(INJECTED.to_string()),
+            author: Some("a\rb\tc\u{7f}".to_string()),
+            link: Some("http://x\r\n127.0.0.1 evil".to_string()),
+            ..EntryMeta::default()
+        };
+        let tags = vec!["dev\nops".to_string()];
+
+        let rendered = render(Some("api"), &tags, &meta).unwrap();
+        assert!(!rendered.contains(|c: char| c.is_control()), "{:?}", rendered);
+
+        let parsed = parse(Some(&rendered));
+        assert_eq!(parsed.comment.as_deref(), Some("api"));
+        assert_eq!(parsed.tags, tags);
+        assert_eq!(parsed.meta, meta);
+    }
+
+    #[test]
+    fn injected_note_stays_on_its_line() {
+        let mut document = HostsDocument::parse("127.0.0.1 localhost\n10.0.0.1 api.local\n");
+        let mut data = document.to_hosts_data();
+        data.entries[1].meta.note = Some(INJECTED.to_string());
+        document.apply(&data);
+
+        let text = document.to_string();
+        assert_eq!(text.lines().count(), 2, "{}", text);
+        assert!(!text.lines().any(|l| l.starts_with("0.0.0.0")));
+
+        let entries = HostsDocument::parse(&text).to_hosts_data().entries;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].meta.note.as_deref(), Some(INJECTED));
+    }
+}
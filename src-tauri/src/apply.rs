@@ -0,0 +1,338 @@
+//! Saving as a transaction: back up, write, verify, and put the old file
+//! back if anything goes wrong.
+//!
+//! After writing, the file is read back and compared byte for byte. The DNS
+//! cache can then be flushed and a chosen set of domains resolved to check
+//! that the system now sees the new mappings. Those two steps are skipped
+//! for any other hosts file (e.g. a container rootfs), which the system
+//! resolver never reads. Any failed step restores the file as it was before
+//! the save.
+//!
+//! A set of changes can also be applied to several targets at once, each
+//! getting its own transaction and report.
+
+use crate::atomic;
+use crate::backup::BackupReason;
+use crate::dns;
+use crate::error::{AppError, ErrorCode};
+use crate::hosts;
+use crate::models::{HostEntry, HostsData};
+use crate::revision::Revision;
+use crate::target;
+use crate::validation::{self, HostIp};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ApplyOptions {
+    /// Flush the DNS cache after writing
+    pub flush_dns: bool,
+    /// Domains that must resolve to the address the saved data maps them to
+    pub verify_domains: Vec<String>,
+    /// Allow dropping or redirecting protected system entries
+    pub allow_protected: bool,
+    /// Recorded with the backup taken before writing
+    pub reason: BackupReason,
+    pub label: Option<String>,
+}
+
+impl ApplyOptions {
+    /// The same options without the steps that only make sense for the
+    /// system hosts file
+    fn without_dns(&self) -> Self {
+        ApplyOptions {
+            flush_dns: false,
+            verify_domains: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Step {
+    Backup,
+    Write,
+    VerifyWrite,
+    FlushDns,
+    VerifyResolution,
+    Rollback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Ok,
+    Failed,
+    /// Does not apply to the hosts file being saved
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    pub step: Step,
+    pub status: StepStatus,
+    pub message: Option<String>,
+    /// Why the step failed
+    pub error: Option<AppError>,
+}
+
+/// What a save did, step by step
+#[derive(Debug, Clone, Serialize)]
+pub struct ApplyReport {
+    /// True when every step passed and the new file is in place
+    pub success: bool,
+    /// Id of the backup taken before writing
+    pub backup: Option<String>,
+    /// Revision now on disk, for the next save
+    pub revision: Option<Revision>,
+    /// True when a failed step was undone by restoring the old file
+    pub rolled_back: bool,
+    pub steps: Vec<StepResult>,
+}
+
+impl ApplyReport {
+    fn record(&mut self, step: Step, result: Result<Option<String>, AppError>) -> bool {
+        let (status, message, error) = match result {
+            Ok(message) => (StepStatus::Ok, message, None),
+            Err(error) => (StepStatus::Failed, Some(error.message.clone()), Some(error)),
+        };
+        self.steps.push(StepResult { step, status, message, error });
+        status == StepStatus::Ok
+    }
+
+    /// Record a DNS step left out because the file isn't the system one
+    fn skip(&mut self, step: Step) -> bool {
+        self.steps.push(StepResult {
+            step,
+            status: StepStatus::Skipped,
+            message: Some("Not the system hosts file, which is the only one the resolver reads".to_string()),
+            error: None,
+        });
+        true
+    }
+}
+
+/// Save `data` and verify the result, rolling back on failure.
+/// Conflicts and other problems found before anything is written are
+/// returned as errors; everything after is reported step by step.
+pub fn apply_hosts(data: &HostsData, options: &ApplyOptions, max_aliases: usize) -> Result<ApplyReport, AppError> {
+    let _lock = hosts::lock_hosts()?;
+    let pending = hosts::prepare_save(data, options.allow_protected, max_aliases)?;
+
+    let mut report = ApplyReport {
+        success: false,
+        backup: None,
+        revision: None,
+        rolled_back: false,
+        steps: Vec::new(),
+    };
+
+    let backup = hosts::backup_hosts_file(options.reason, options.label.as_deref());
+    report.backup = backup.as_ref().ok().cloned();
+    if !report.record(Step::Backup, backup.map(Some)) {
+        return Ok(report);
+    }
+
+    // Checking the resolver against another file would always fail and
+    // roll back a good write
+    let system = target::is_system_hosts(&hosts::get_hosts_path());
+
+    let written = hosts::write_pending(&pending);
+    report.revision = written.as_ref().ok().cloned();
+    let mut passed = report.record(Step::Write, written.map(|_| None))
+        && report.record(Step::VerifyWrite, verify_write(&pending.content));
+    if passed && options.flush_dns {
+        passed = if system {
+            report.record(Step::FlushDns, dns::flush_dns_cache().map(|_| None))
+        } else {
+            report.skip(Step::FlushDns)
+        };
+    }
+    if passed && !options.verify_domains.is_empty() {
+        passed = if system {
+            report.record(Step::VerifyResolution, verify_resolution(data, &options.verify_domains))
+        } else {
+            report.skip(Step::VerifyResolution)
+        };
+    }
+
+    if passed {
+        report.success = true;
+        return Ok(report);
+    }
+
+    // Put the file back as it was before the save
+    let path = hosts::get_hosts_path();
+    let restored = atomic::write_atomic(&path, &pending.original)
+        .map_err(|e| AppError::io(ErrorCode::WriteFailed, "restore hosts file", &path, &e));
+    report.rolled_back = restored.is_ok();
+    report.revision = None;
+    report.record(Step::Rollback, restored.map(|_| None));
+
+    if report.rolled_back && options.flush_dns && system {
+        let _ = dns::flush_dns_cache();
+    }
+    Ok(report)
+}
+
+/// Read the file back and compare it with what was meant to be written
+fn verify_write(expected: &[u8]) -> Result<Option<String>, AppError> {
+    let path = hosts::get_hosts_path();
+    let actual = fs::read(&path)
+        .map_err(|e| AppError::io(ErrorCode::ReadFailed, "read hosts file back", &path, &e))?;
+
+    if actual == expected {
+        Ok(None)
+    } else {
+        let message = format!(
+            "Hosts file on disk does not match what was written ({} bytes expected, {} found)",
+            expected.len(),
+            actual.len()
+        );
+        Err(AppError::new(ErrorCode::VerifyFailed, message).with_path(&path))
+    }
+}
+
+/// Check each domain resolves to the address its enabled entry maps it to
+fn verify_resolution(data: &HostsData, domains: &[String]) -> Result<Option<String>, AppError> {
+    let mut failures = Vec::new();
+
+    // Names are compared and resolved in the punycode form written to the file
+    let ascii = |name: &str| validation::hostname_to_ascii(name).unwrap_or_else(|_| name.to_string());
+
+    for domain in domains {
+        let wanted = ascii(domain);
+        let expected = data
+            .entries
+            .iter()
+            .filter(|e| e.enabled)
+            .find(|e| e.domains.iter().any(|d| ascii(d).eq_ignore_ascii_case(&wanted)))
+            .and_then(|e| e.ip.parse::<HostIp>().ok());
+        let Some(expected) = expected else {
+            failures.push(format!("{} is not mapped by any enabled entry", domain));
+            continue;
+        };
+
+        match dns::resolve_all(&wanted) {
+            Ok(addrs) if addrs.contains(&expected.addr) => {}
+            Ok(addrs) => {
+                let found: Vec<String> = addrs.iter().map(|a| a.to_string()).collect();
+                failures.push(format!(
+                    "{} resolves to [{}], expected {}",
+                    domain,
+                    found.join(", "),
+                    expected.addr
+                ));
+            }
+            Err(e) => failures.push(format!("{}: {}", domain, e)),
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(Some(format!("{} domain(s) resolve as expected", domains.len())))
+    } else {
+        Err(AppError::new(ErrorCode::VerifyFailed, failures.join("\n")))
+    }
+}
+
+/// A change that can be applied to any hosts file, independent of its
+/// layout
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+pub enum Change {
+    /// Add an entry, or replace the entry mapping its first hostname
+    Upsert { entry: Box<HostEntry> },
+    /// Remove a hostname from every entry, dropping entries left empty
+    Remove { domain: String },
+    /// Enable or disable every entry mapping a hostname
+    SetEnabled { domain: String, enabled: bool },
+}
+
+/// Outcome of a multi-target apply for one target
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetResult {
+    pub target: String,
+    pub report: Option<ApplyReport>,
+    pub error: Option<AppError>,
+}
+
+/// Apply `changes` to each target in turn. A failure on one target is
+/// reported and does not stop the others. The DNS flush and domain checks
+/// of `options` only run for a target that is the system hosts file; the
+/// others are saved without them.
+pub fn apply_to_targets(
+    targets: &[String],
+    changes: &[Change],
+    options: &ApplyOptions,
+    max_aliases: usize,
+) -> Vec<TargetResult> {
+    targets
+        .iter()
+        .map(|id| {
+            let result = target::within(Some(id), || {
+                let mut data = hosts::read_hosts(max_aliases)?;
+                apply_changes(&mut data, changes);
+                if target::is_system_hosts(&hosts::get_hosts_path()) {
+                    apply_hosts(&data, options, max_aliases)
+                } else {
+                    apply_hosts(&data, &options.without_dns(), max_aliases)
+                }
+            });
+
+            let (report, error) = match result {
+                Ok(report) => (Some(report), None),
+                Err(error) => (None, Some(error)),
+            };
+            TargetResult {
+                target: id.clone(),
+                report,
+                error,
+            }
+        })
+        .collect()
+}
+
+/// Apply `changes` to data read from a hosts file
+pub fn apply_changes(data: &mut HostsData, changes: &[Change]) {
+    let maps = |entry: &HostEntry, domain: &str| entry.domains.iter().any(|d| d.eq_ignore_ascii_case(domain));
+
+    for change in changes {
+        match change {
+            Change::Upsert { entry } => {
+                let mut entry = entry.as_ref().clone();
+                let first = entry.domains.first().cloned().unwrap_or_default();
+
+                match data.entries.iter_mut().find(|e| maps(e, &first)) {
+                    Some(existing) => {
+                        entry.id = existing.id.clone();
+                        if entry.section.is_none() {
+                            entry.section = existing.section.clone();
+                        }
+                        *existing = entry;
+                    }
+                    None => {
+                        // Section ids are specific to each file
+                        entry.id = String::new();
+                        if !data.sections.iter().any(|s| Some(&s.id) == entry.section.as_ref()) {
+                            entry.section = None;
+                        }
+                        data.entries.push(entry);
+                    }
+                }
+            }
+            Change::Remove { domain } => {
+                for entry in &mut data.entries {
+                    entry.domains.retain(|d| !d.eq_ignore_ascii_case(domain));
+                }
+                data.entries.retain(|e| !e.domains.is_empty());
+            }
+            Change::SetEnabled { domain, enabled } => {
+                for entry in data.entries.iter_mut().filter(|e| maps(e, domain)) {
+                    entry.enabled = *enabled;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Crash-safe replacement of the hosts file.
+//!
+//! The new content is written to a temporary file next to the hosts file,
+//! flushed to disk, given the original owner, mode and extended attributes,
+//! and then renamed over the original in one step. A crash at any point
+//! leaves either the old or the new file, never a truncated one.
+//!
+//! Some systems can't replace the file: a bind-mounted `/etc/hosts` in a
+//! container, a directory we may not create files in, or a file held open
+//! on Windows. There the file is rewritten in place while holding a lock.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Replace the file at `path` with `contents`
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    match replace(path, contents) {
+        Ok(()) => Ok(()),
+        Err(Failure::Write(e)) => Err(e),
+        Err(Failure::Replace) => write_in_place(path, contents),
+    }
+}
+
+/// Why replacing failed. Only a failure to replace falls back to writing
+/// in place, whose own error is then the one reported; a failed write
+/// (e.g. a full disk) must not touch the original.
+enum Failure {
+    Write(io::Error),
+    Replace,
+}
+
+fn replace(path: &Path, contents: &[u8]) -> Result<(), Failure> {
+    let temp_path = temp_path(path).map_err(|_| Failure::Replace)?;
+    let mut temp = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp_path)
+        .map_err(|_| Failure::Replace)?;
+
+    let result = temp
+        .write_all(contents)
+        .and_then(|_| temp.sync_all())
+        .map_err(Failure::Write)
+        .and_then(|_| copy_metadata(path, &temp).map_err(|_| Failure::Replace))
+        .and_then(|_| {
+            drop(temp);
+            rename_over(&temp_path, path).map_err(|_| Failure::Replace)
+        });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Temporary file in the same directory, so the rename stays on one filesystem
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no parent directory"))?;
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    Ok(dir.join(format!(".{}.easyhosts-{}.tmp", name, std::process::id())))
+}
+
+/// Overwrite the file in place under an exclusive lock, then cut off
+/// whatever is left of the old content
+fn write_in_place(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    file.lock()?;
+
+    let result = file
+        .write_all(contents)
+        .and_then(|_| file.set_len(contents.len() as u64))
+        .and_then(|_| file.sync_all());
+
+    let _ = file.unlock();
+    result
+}
+
+/// Give the temporary file the owner, mode and extended attributes of the
+/// file it replaces
+#[cfg(unix)]
+fn copy_metadata(original: &Path, temp: &File) -> io::Result<()> {
+    use std::os::unix::fs::{fchown, MetadataExt};
+
+    let metadata = match fs::metadata(original) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    fchown(temp, Some(metadata.uid()), Some(metadata.gid()))?;
+    temp.set_permissions(metadata.permissions())?;
+    xattr::copy(original, temp)
+}
+
+/// `ReplaceFileW` keeps the ACL, owner and attributes of the original
+#[cfg(windows)]
+fn copy_metadata(_original: &Path, _temp: &File) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn rename_over(temp: &Path, path: &Path) -> io::Result<()> {
+    fs::rename(temp, path)?;
+
+    // Make the rename itself durable
+    if let Some(dir) = path.parent() {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn rename_over(temp: &Path, path: &Path) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        ReplaceFileW, REPLACEFILE_IGNORE_MERGE_ERRORS, REPLACEFILE_WRITE_THROUGH,
+    };
+
+    if !path.exists() {
+        return fs::rename(temp, path);
+    }
+
+    let wide = |p: &Path| p.as_os_str().encode_wide().chain(Some(0)).collect::<Vec<u16>>();
+    let (replaced, replacement) = (wide(path), wide(temp));
+
+    let ok = unsafe {
+        ReplaceFileW(
+            replaced.as_ptr(),
+            replacement.as_ptr(),
+            std::ptr::null(),
+            REPLACEFILE_WRITE_THROUGH | REPLACEFILE_IGNORE_MERGE_ERRORS,
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod xattr {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::ptr;
+
+    /// Copy every extended attribute (SELinux labels included)
+    pub fn copy(from: &Path, to: &File) -> io::Result<()> {
+        let path = CString::new(from.as_os_str().as_bytes())?;
+
+        let names = read_buffer(|buf, len| unsafe { libc::listxattr(path.as_ptr(), buf.cast(), len) });
+        let names = match names {
+            Ok(names) => names,
+            // The filesystem has no extended attributes at all
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        for name in names.split(|&b| b == 0).filter(|n| !n.is_empty()) {
+            let name = CString::new(name)?;
+            let value = read_buffer(|buf, len| unsafe {
+                libc::getxattr(path.as_ptr(), name.as_ptr(), buf.cast(), len)
+            })?;
+            let rc = unsafe {
+                libc::fsetxattr(to.as_raw_fd(), name.as_ptr(), value.as_ptr().cast(), value.len(), 0)
+            };
+            if rc != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Call a size-then-fill libc function
+    fn read_buffer(call: impl Fn(*mut u8, usize) -> isize) -> io::Result<Vec<u8>> {
+        let len = call(ptr::null_mut(), 0);
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut buf = vec![0u8; len as usize];
+        let len = call(buf.as_mut_ptr(), buf.len());
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        buf.truncate(len as usize);
+        Ok(buf)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod xattr {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::ptr;
+
+    /// Copy every extended attribute
+    pub fn copy(from: &Path, to: &File) -> io::Result<()> {
+        let path = CString::new(from.as_os_str().as_bytes())?;
+
+        let names = read_buffer(|buf, len| unsafe { libc::listxattr(path.as_ptr(), buf.cast(), len, 0) })?;
+        for name in names.split(|&b| b == 0).filter(|n| !n.is_empty()) {
+            let name = CString::new(name)?;
+            let value = read_buffer(|buf, len| unsafe {
+                libc::getxattr(path.as_ptr(), name.as_ptr(), buf.cast(), len, 0, 0)
+            })?;
+            let rc = unsafe {
+                libc::fsetxattr(to.as_raw_fd(), name.as_ptr(), value.as_ptr().cast(), value.len(), 0, 0)
+            };
+            if rc != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Call a size-then-fill libc function
+    fn read_buffer(call: impl Fn(*mut u8, usize) -> isize) -> io::Result<Vec<u8>> {
+        let len = call(ptr::null_mut(), 0);
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut buf = vec![0u8; len as usize];
+        let len = call(buf.as_mut_ptr(), buf.len());
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        buf.truncate(len as usize);
+        Ok(buf)
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "macos"))))]
+mod xattr {
+    use std::fs::File;
+    use std::io;
+    use std::path::Path;
+
+    pub fn copy(_from: &Path, _to: &File) -> io::Result<()> {
+        Ok(())
+    }
+}
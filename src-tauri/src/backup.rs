@@ -0,0 +1,630 @@
+//! Backups of the hosts file, kept in the app's data directory.
+//!
+//! Each hosts file gets its own folder in the store, named after a hash of
+//! its path, so backups of different targets never mix. A backup is a
+//! snapshot in the folder's index (`snapshots.json`) recording why it was
+//! taken, when, from where and an optional label. Its content is stored
+//! gzip-compressed under `objects/`, named by its SHA-256, so backups of
+//! unchanged content share one copy. Content is checked against its hash
+//! whenever it is read; a backup whose data is missing or damaged is
+//! reported and can't be restored.
+//!
+//! Backups made before the store existed (`hosts.bak_*` next to the hosts
+//! file) are imported the first time that file is backed up or its backups
+//! are listed. After every backup the folder is pruned by the retention
+//! policy; labelled backups are never pruned.
+//!
+//! The index is rewritten atomically. Callers hold the hosts lock
+//! (`hosts::lock_hosts`, kept in the same folder) around every call that
+//! changes it, so concurrent processes don't drop each other's backups.
+
+use crate::atomic;
+use crate::error::{AppError, ErrorCode};
+use chrono::{NaiveDate, NaiveDateTime};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+const BACKUP_PREFIX: &str = "hosts.bak_";
+const INDEX: &str = "snapshots.json";
+const OBJECTS: &str = "objects";
+/// Left in a hosts file's folder once its old backups have all moved
+const MIGRATED_MARKER: &str = ".migrated";
+
+/// Root of the store, set at startup from the app data directory
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+static RETENTION: RwLock<RetentionPolicy> = RwLock::new(RetentionPolicy::DEFAULT);
+/// Profile active in the UI, recorded with each backup
+static PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupInfo {
+    /// `YYYYMMDD_HHMMSS` when taken, with a counter for backups in the same second
+    pub id: String,
+    /// Size of the backed up file
+    pub size: u64,
+    /// Compressed size on disk, shared with backups of the same content
+    pub stored_size: u64,
+    pub meta: BackupMeta,
+    /// Why the backup can't be restored
+    pub error: Option<AppError>,
+}
+
+/// Why a backup was taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackupReason {
+    /// Saving edits made in the editor
+    #[default]
+    Save,
+    /// Changing a single entry or section
+    Edit,
+    /// Reformatting the file or converting its encoding
+    Format,
+    /// Adding the EasyHosts managed block
+    Migrate,
+    Restore,
+    Reset,
+    ProfileSwitch,
+    RemoteUpdate,
+    /// Taken on request, usually with a label
+    Manual,
+    /// Made by an older version, before reasons were recorded
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupMeta {
+    pub reason: BackupReason,
+    /// Set by the user; labelled backups are never pruned
+    pub label: Option<String>,
+    /// When the backup was taken, RFC 3339
+    pub created: String,
+    /// SHA-256 of the backed up bytes, hex encoded; also names the stored copy
+    pub checksum: String,
+    /// Hosts file the backup was taken from
+    pub source: String,
+    pub app_version: String,
+    /// Profile active in the UI at the time
+    pub profile: Option<String>,
+}
+
+/// Entry in a folder's snapshot index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    id: String,
+    size: u64,
+    meta: BackupMeta,
+}
+
+/// Which backups to keep. A backup is kept if any limit that is set keeps
+/// it; with neither count set, all are kept. The newest backup is never
+/// deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Keep this many of the newest backups
+    pub keep_last: Option<usize>,
+    /// Keep the newest backup of each of this many past days
+    pub keep_daily_days: Option<u32>,
+    /// Then delete the oldest backups beyond this total size
+    pub max_total_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    const DEFAULT: RetentionPolicy = RetentionPolicy {
+        keep_last: Some(20),
+        keep_daily_days: Some(30),
+        max_total_bytes: Some(50 * 1024 * 1024),
+    };
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy::DEFAULT
+    }
+}
+
+pub fn set_root(root: PathBuf) {
+    let _ = ROOT.set(root);
+}
+
+pub fn set_retention(policy: RetentionPolicy) -> Result<(), AppError> {
+    *RETENTION
+        .write()
+        .map_err(|_| AppError::settings("update backup retention"))? = policy;
+    Ok(())
+}
+
+fn retention() -> RetentionPolicy {
+    RETENTION.read().map(|p| p.clone()).unwrap_or_default()
+}
+
+pub fn set_profile(name: Option<String>) {
+    if let Ok(mut profile) = PROFILE.write() {
+        *profile = name.filter(|n| !n.trim().is_empty());
+    }
+}
+
+/// Folder holding the backups of the hosts file at `hosts_path`
+pub fn dir_for(hosts_path: &Path) -> Result<PathBuf, AppError> {
+    let root = ROOT
+        .get()
+        .ok_or_else(|| AppError::new(ErrorCode::BackupFailed, "The backup store is not available"))?;
+    let hash = format!("{:x}", Sha256::digest(hosts_path.to_string_lossy().as_bytes()));
+    Ok(root.join(&hash[..16]))
+}
+
+/// Store `content` as a new backup of `hosts_path`, returning its id
+pub fn create(
+    hosts_path: &Path,
+    content: &[u8],
+    reason: BackupReason,
+    label: Option<&str>,
+) -> Result<String, AppError> {
+    migrate(hosts_path);
+
+    let dir = dir_for(hosts_path)?;
+    let mut snapshots = read_index(&dir)?;
+
+    let meta = BackupMeta {
+        reason,
+        label: clean_label(label),
+        created: chrono::Local::now().to_rfc3339(),
+        checksum: format!("{:x}", Sha256::digest(content)),
+        source: hosts_path.to_string_lossy().to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        profile: PROFILE.read().ok().and_then(|p| p.clone()),
+    };
+    store_blob(&dir, &meta.checksum, content)?;
+
+    let id = unique_id(&snapshots, &chrono::Local::now().format("%Y%m%d_%H%M%S").to_string());
+    snapshots.push(Snapshot { id: id.clone(), size: content.len() as u64, meta });
+    write_index(&dir, &snapshots)?;
+
+    if let Err(e) = prune(&dir) {
+        eprintln!("Failed to prune backups: {}", e);
+    }
+    Ok(id)
+}
+
+/// Backups of `hosts_path`, newest first, each checked for damage
+pub fn list(hosts_path: &Path) -> Result<Vec<BackupInfo>, AppError> {
+    migrate(hosts_path);
+
+    let dir = dir_for(hosts_path)?;
+    // Content shared by several backups is only checked once
+    let mut checked: HashMap<String, (u64, Option<AppError>)> = HashMap::new();
+
+    Ok(read_index(&dir)?
+        .into_iter()
+        .map(|snapshot| {
+            let (stored_size, error) = checked
+                .entry(snapshot.meta.checksum.clone())
+                .or_insert_with(|| {
+                    let path = blob_path(&dir, &snapshot.meta.checksum);
+                    let stored_size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                    (stored_size, read_blob(&path, &snapshot.meta.checksum).err())
+                })
+                .clone();
+            BackupInfo {
+                id: snapshot.id,
+                size: snapshot.size,
+                stored_size,
+                meta: snapshot.meta,
+                error,
+            }
+        })
+        .collect())
+}
+
+/// Content of a backup, refused if it no longer matches its checksum
+pub fn read(hosts_path: &Path, id: &str) -> Result<Vec<u8>, AppError> {
+    let dir = dir_for(hosts_path)?;
+    let snapshots = read_index(&dir)?;
+    let snapshot = &snapshots[position(&snapshots, id)?];
+    read_blob(&blob_path(&dir, &snapshot.meta.checksum), &snapshot.meta.checksum)
+}
+
+/// Delete a backup, and its stored content if no other backup shares it
+pub fn delete(hosts_path: &Path, id: &str) -> Result<(), AppError> {
+    let dir = dir_for(hosts_path)?;
+    let mut snapshots = read_index(&dir)?;
+    snapshots.remove(position(&snapshots, id)?);
+    write_index(&dir, &snapshots)?;
+    collect_garbage(&dir, &snapshots)
+}
+
+/// Label a backup, or clear its label with `None`
+pub fn set_label(hosts_path: &Path, id: &str, label: Option<&str>) -> Result<BackupMeta, AppError> {
+    let dir = dir_for(hosts_path)?;
+    let mut snapshots = read_index(&dir)?;
+    let i = position(&snapshots, id)?;
+    snapshots[i].meta.label = clean_label(label);
+    write_index(&dir, &snapshots)?;
+    Ok(snapshots[i].meta.clone())
+}
+
+fn clean_label(label: Option<&str>) -> Option<String> {
+    label.map(str::trim).filter(|l| !l.is_empty()).map(String::from)
+}
+
+fn position(snapshots: &[Snapshot], id: &str) -> Result<usize, AppError> {
+    snapshots
+        .iter()
+        .position(|s| s.id == id)
+        .ok_or_else(|| AppError::new(ErrorCode::BackupNotFound, format!("Backup {} does not exist", id)))
+}
+
+/// `timestamp`, with a counter past any backup already taken that second
+fn unique_id(snapshots: &[Snapshot], timestamp: &str) -> String {
+    let last = snapshots
+        .iter()
+        .map(|s| id_order(&s.id))
+        .filter(|(t, _)| *t == timestamp)
+        .map(|(_, n)| n)
+        .max();
+    match last {
+        Some(n) => format!("{}_{}", timestamp, n + 1),
+        None => timestamp.to_string(),
+    }
+}
+
+/// The folder's snapshots, newest first; none if it has no index yet
+fn read_index(dir: &Path) -> Result<Vec<Snapshot>, AppError> {
+    let path = dir.join(INDEX);
+    let json = match fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::io(ErrorCode::ReadFailed, "read backup index", &path, &e)),
+    };
+
+    let mut snapshots: Vec<Snapshot> = serde_json::from_str(&json).map_err(|e| {
+        AppError::new(ErrorCode::CorruptedBackup, format!("Backup index {} is damaged: {}", path.display(), e))
+            .with_path(&path)
+    })?;
+    snapshots.sort_by(|a, b| id_order(&b.id).cmp(&id_order(&a.id)));
+    Ok(snapshots)
+}
+
+/// Ids sort by timestamp (`YYYYMMDD_HHMMSS`), then by counter
+fn id_order(id: &str) -> (&str, u32) {
+    let timestamp = id.get(..15).unwrap_or(id);
+    let counter = id.get(16..).and_then(|n| n.parse().ok()).unwrap_or(0);
+    (timestamp, counter)
+}
+
+fn write_index(dir: &Path, snapshots: &[Snapshot]) -> Result<(), AppError> {
+    fs::create_dir_all(dir)
+        .map_err(|e| AppError::io(ErrorCode::BackupFailed, "create backup directory", dir, &e))?;
+
+    let path = dir.join(INDEX);
+    let json = serde_json::to_string_pretty(snapshots)
+        .map_err(|e| AppError::new(ErrorCode::BackupFailed, format!("Failed to serialize backup index: {}", e)))?;
+    atomic::write_atomic(&path, json.as_bytes())
+        .map_err(|e| AppError::io(ErrorCode::BackupFailed, "write backup index", &path, &e))
+}
+
+fn blob_path(dir: &Path, checksum: &str) -> PathBuf {
+    dir.join(OBJECTS).join(format!("{}.gz", checksum))
+}
+
+/// Compress and store `content` under its checksum, unless an intact copy
+/// is already there
+fn store_blob(dir: &Path, checksum: &str, content: &[u8]) -> Result<(), AppError> {
+    let path = blob_path(dir, checksum);
+    if read_blob(&path, checksum).is_ok() {
+        return Ok(());
+    }
+
+    let objects = dir.join(OBJECTS);
+    fs::create_dir_all(&objects)
+        .map_err(|e| AppError::io(ErrorCode::BackupFailed, "create backup directory", &objects, &e))?;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let compressed = encoder
+        .write_all(content)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| AppError::io(ErrorCode::BackupFailed, "compress backup", &path, &e))?;
+    atomic::write_atomic(&path, &compressed)
+        .map_err(|e| AppError::io(ErrorCode::BackupFailed, "write backup", &path, &e))
+}
+
+/// Decompress stored content and check it against `checksum`
+fn read_blob(path: &Path, checksum: &str) -> Result<Vec<u8>, AppError> {
+    let compressed = fs::read(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => damaged(path, "its data is missing"),
+        _ => AppError::io(ErrorCode::ReadFailed, "read backup", path, &e),
+    })?;
+
+    let mut content = Vec::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut content)
+        .map_err(|e| damaged(path, &format!("its data can't be decompressed ({})", e)))?;
+
+    if format!("{:x}", Sha256::digest(&content)) != checksum {
+        return Err(damaged(path, "its content does not match its checksum"));
+    }
+    Ok(content)
+}
+
+fn damaged(path: &Path, why: &str) -> AppError {
+    AppError::new(ErrorCode::CorruptedBackup, format!("Backup {} is damaged: {}", path.display(), why)).with_path(path)
+}
+
+/// Delete stored content no backup refers to any more
+fn collect_garbage(dir: &Path, snapshots: &[Snapshot]) -> Result<(), AppError> {
+    let used: HashSet<String> = snapshots.iter().map(|s| format!("{}.gz", s.meta.checksum)).collect();
+    let Ok(entries) = fs::read_dir(dir.join(OBJECTS)) else {
+        return Ok(());
+    };
+
+    for entry in entries.flatten() {
+        if !used.contains(entry.file_name().to_string_lossy().as_ref()) {
+            let path = entry.path();
+            fs::remove_file(&path)
+                .map_err(|e| AppError::io(ErrorCode::WriteFailed, "delete unused backup", &path, &e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Import `hosts.bak_*` copies kept next to the hosts file by older
+/// versions. Files that can't be removed from there (e.g. without root) are
+/// imported once and removed on a later run.
+fn migrate(hosts_path: &Path) {
+    let (Ok(dir), Some(legacy_dir)) = (dir_for(hosts_path), hosts_path.parent()) else {
+        return;
+    };
+    if dir.join(MIGRATED_MARKER).exists() {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(legacy_dir) else {
+        return;
+    };
+    let Ok(mut snapshots) = read_index(&dir) else {
+        return;
+    };
+
+    let mut imported = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let (Some(timestamp), true) = (name.strip_prefix(BACKUP_PREFIX), entry.path().is_file()) else {
+            continue;
+        };
+        let Ok(content) = fs::read(entry.path()) else {
+            continue;
+        };
+
+        let checksum = format!("{:x}", Sha256::digest(&content));
+        // Still there after an earlier import
+        let known = snapshots.iter().any(|s| s.meta.checksum == checksum && id_order(&s.id).0 == timestamp);
+        if !known {
+            if store_blob(&dir, &checksum, &content).is_err() {
+                continue;
+            }
+            let meta = BackupMeta {
+                reason: BackupReason::Unknown,
+                label: None,
+                created: NaiveDateTime::parse_from_str(timestamp, "%Y%m%d_%H%M%S")
+                    .ok()
+                    .and_then(|t| t.and_local_timezone(chrono::Local).earliest())
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_default(),
+                checksum,
+                source: hosts_path.to_string_lossy().to_string(),
+                app_version: String::new(),
+                profile: None,
+            };
+            let id = unique_id(&snapshots, timestamp);
+            snapshots.push(Snapshot { id, size: content.len() as u64, meta });
+        }
+        imported.push(entry.path());
+    }
+    if write_index(&dir, &snapshots).is_err() {
+        return;
+    }
+
+    let mut complete = true;
+    for path in imported {
+        complete &= fs::remove_file(path).is_ok();
+    }
+    if complete {
+        let _ = fs::write(dir.join(MIGRATED_MARKER), "");
+    }
+    if let Err(e) = prune(&dir) {
+        eprintln!("Failed to prune backups: {}", e);
+    }
+}
+
+/// Delete the unlabelled backups in `dir` the retention policy doesn't keep
+fn prune(dir: &Path) -> Result<(), AppError> {
+    let (mut remaining, unlabelled): (Vec<Snapshot>, Vec<Snapshot>) =
+        read_index(dir)?.into_iter().partition(|s| s.meta.label.is_some());
+    let stored_size = |checksum: &str| fs::metadata(blob_path(dir, checksum)).map(|m| m.len()).unwrap_or(0);
+    let Some(keep) = kept(&retention(), &unlabelled, stored_size, chrono::Local::now().date_naive()) else {
+        return Ok(());
+    };
+
+    remaining.extend(unlabelled.into_iter().zip(keep).filter_map(|(s, keep)| keep.then_some(s)));
+    write_index(dir, &remaining)?;
+    collect_garbage(dir, &remaining)
+}
+
+/// For each backup (newest first), whether to keep it; `None` if there is
+/// nothing to delete. Content shared by several backups counts once
+/// towards the size limit.
+fn kept(
+    policy: &RetentionPolicy,
+    backups: &[Snapshot],
+    stored_size: impl Fn(&str) -> u64,
+    today: NaiveDate,
+) -> Option<Vec<bool>> {
+    if backups.is_empty() {
+        return None;
+    }
+
+    let limited = policy.keep_last.is_some() || policy.keep_daily_days.is_some();
+    let mut keep = vec![!limited; backups.len()];
+
+    if let Some(n) = policy.keep_last {
+        keep.iter_mut().take(n).for_each(|k| *k = true);
+    }
+    if let Some(days) = policy.keep_daily_days {
+        let mut seen = HashSet::new();
+        for (i, backup) in backups.iter().enumerate() {
+            let Some(date) = backup_date(&backup.id) else {
+                continue;
+            };
+            if (today - date).num_days() < i64::from(days) && seen.insert(date) {
+                keep[i] = true;
+            }
+        }
+    }
+    keep[0] = true;
+
+    if let Some(max) = policy.max_total_bytes {
+        let mut counted = HashSet::new();
+        let mut total = 0;
+        let mut full = false;
+        for (i, backup) in backups.iter().enumerate() {
+            let checksum = backup.meta.checksum.as_str();
+            if !keep[i] || counted.contains(checksum) {
+                continue;
+            }
+            total += stored_size(checksum);
+            full |= total > max;
+            keep[i] = i == 0 || !full;
+            if keep[i] {
+                counted.insert(checksum);
+            }
+        }
+    }
+
+    (!keep.iter().all(|k| *k)).then_some(keep)
+}
+
+/// Day a backup was made, from `YYYYMMDD_HHMMSS`
+fn backup_date(timestamp: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(timestamp.get(..8)?, "%Y%m%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(id: &str, checksum: &str, label: Option<&str>) -> Snapshot {
+        Snapshot {
+            id: id.to_string(),
+            size: 0,
+            meta: BackupMeta {
+                reason: BackupReason::Save,
+                label: label.map(String::from),
+                created: String::new(),
+                checksum: checksum.to_string(),
+                source: String::new(),
+                app_version: String::new(),
+                profile: None,
+            },
+        }
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 31).unwrap()
+    }
+
+    /// Snapshots one per day back from `today()`, newest first
+    fn daily(count: u32) -> Vec<Snapshot> {
+        (0..count)
+            .map(|i| {
+                let day = today() - chrono::Days::new(u64::from(i));
+                snapshot(&format!("{}_120000", day.format("%Y%m%d")), &format!("c{}", i), None)
+            })
+            .collect()
+    }
+
+    /// A fresh folder under the temp directory, with the store rooted there
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("easyhosts-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        set_root(std::env::temp_dir().join(format!("easyhosts-store-{}", std::process::id())));
+        dir
+    }
+
+    #[test]
+    fn count_retention_keeps_the_newest() {
+        let policy = RetentionPolicy { keep_last: Some(3), keep_daily_days: None, max_total_bytes: None };
+        let keep = kept(&policy, &daily(5), |_| 0, today()).unwrap();
+        assert_eq!(keep, [true, true, true, false, false]);
+
+        let policy = RetentionPolicy { keep_last: Some(5), ..policy };
+        assert_eq!(kept(&policy, &daily(5), |_| 0, today()), None);
+    }
+
+    #[test]
+    fn age_retention_keeps_one_backup_a_day() {
+        let policy = RetentionPolicy { keep_last: None, keep_daily_days: Some(2), max_total_bytes: None };
+        let mut backups = vec![snapshot("20240331_180000", "late", None)];
+        backups.extend(daily(4));
+
+        let keep = kept(&policy, &backups, |_| 0, today()).unwrap();
+        // The newest of today and of yesterday
+        assert_eq!(keep, [true, false, true, false, false]);
+    }
+
+    #[test]
+    fn labelled_backups_are_never_pruned() {
+        let dir = scratch_dir("labelled");
+        let checksum = format!("{:x}", Sha256::digest(b"hosts"));
+        store_blob(&dir, &checksum, b"hosts").unwrap();
+
+        // Older than the default daily window and beyond the default count
+        let mut snapshots: Vec<Snapshot> = daily(25).into_iter().map(|s| snapshot(&s.id, &checksum, None)).collect();
+        snapshots[24].meta.label = Some("before upgrade".to_string());
+        write_index(&dir, &snapshots).unwrap();
+
+        prune(&dir).unwrap();
+        let remaining = read_index(&dir).unwrap();
+        let keep_last = RetentionPolicy::DEFAULT.keep_last.unwrap();
+        assert_eq!(remaining.len(), keep_last + 1);
+        assert_eq!(remaining.last().map(|s| s.id.as_str()), Some(snapshots[24].id.as_str()));
+        assert!(blob_path(&dir, &checksum).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn migrates_old_backups_next_to_the_hosts_file() {
+        let etc = scratch_dir("migrate");
+        let hosts = etc.join("hosts");
+        fs::write(&hosts, "127.0.0.1 localhost\n").unwrap();
+        fs::write(etc.join("hosts.bak_20240101_120000"), "10.0.0.1 old.local\n").unwrap();
+        // Not the old layout: a folder, a copy one level down and other files
+        fs::create_dir(etc.join("hosts.bak_20240102_120000")).unwrap();
+        fs::create_dir(etc.join("backups")).unwrap();
+        fs::write(etc.join("backups").join("hosts.bak_20240103_120000"), "10.0.0.3 nested.local\n").unwrap();
+        fs::write(etc.join("hosts.orig"), "10.0.0.4 orig.local\n").unwrap();
+
+        let backups = list(&hosts).unwrap();
+        let ids: Vec<&str> = backups.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, ["20240101_120000"]);
+        assert_eq!(backups[0].meta.reason, BackupReason::Unknown);
+        assert_eq!(read(&hosts, "20240101_120000").unwrap(), b"10.0.0.1 old.local\n");
+
+        assert!(!etc.join("hosts.bak_20240101_120000").exists());
+        assert!(etc.join("backups").join("hosts.bak_20240103_120000").exists());
+        assert!(etc.join("hosts.orig").exists());
+
+        // Listing again imports nothing twice
+        assert_eq!(list(&hosts).unwrap().len(), 1);
+        fs::remove_dir_all(dir_for(&hosts).unwrap()).unwrap();
+        fs::remove_dir_all(&etc).unwrap();
+    }
+}
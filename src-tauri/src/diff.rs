@@ -0,0 +1,376 @@
+//! Diffs between two versions of a hosts file, line by line and entry by
+//! entry.
+
+use crate::models::HostEntry;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::ops::Range;
+
+/// Lines of context around each change in a unified diff
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// What happened to an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// Enabled or disabled
+    Toggled,
+    IpChanged,
+    /// Hostnames added, removed or reordered
+    AliasesChanged,
+    /// Moved to another section
+    SectionChanged,
+    /// Comment, tags or metadata edited
+    CommentChanged,
+}
+
+/// One entry that differs between two versions
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryChange {
+    pub id: String,
+    /// First hostname, for display
+    pub domain: String,
+    pub kinds: Vec<ChangeKind>,
+    pub before: Option<HostEntry>,
+    pub after: Option<HostEntry>,
+}
+
+/// What changes between two versions of the hosts file
+#[derive(Debug, Clone, Serialize)]
+pub struct HostsDiff {
+    pub entries: Vec<EntryChange>,
+    /// Unified diff of the file text, empty when nothing changes
+    pub text: String,
+}
+
+/// Compare entries by id. Entries without an id (new in the UI) count as
+/// added. Versions read separately need their ids lined up first (see
+/// `document::carry_entry_ids`). Changes come in the order of `new`,
+/// followed by removals.
+pub fn entry_changes(old: &[HostEntry], new: &[HostEntry]) -> Vec<EntryChange> {
+    let old_by_id: HashMap<&str, &HostEntry> = old
+        .iter()
+        .filter(|e| !e.id.is_empty())
+        .map(|e| (e.id.as_str(), e))
+        .collect();
+    let mut kept = HashSet::new();
+    let mut changes = Vec::new();
+
+    for entry in new {
+        let Some(before) = old_by_id.get(entry.id.as_str()).copied().filter(|_| !entry.id.is_empty()) else {
+            changes.push(change(entry, None, Some(entry), vec![ChangeKind::Added]));
+            continue;
+        };
+        kept.insert(entry.id.as_str());
+
+        let mut kinds = Vec::new();
+        if before.enabled != entry.enabled {
+            kinds.push(ChangeKind::Toggled);
+        }
+        if before.ip != entry.ip {
+            kinds.push(ChangeKind::IpChanged);
+        }
+        if before.domains != entry.domains {
+            kinds.push(ChangeKind::AliasesChanged);
+        }
+        if before.section != entry.section {
+            kinds.push(ChangeKind::SectionChanged);
+        }
+        if before.comment != entry.comment || before.tags != entry.tags || before.meta != entry.meta {
+            kinds.push(ChangeKind::CommentChanged);
+        }
+        if !kinds.is_empty() {
+            changes.push(change(entry, Some(before), Some(entry), kinds));
+        }
+    }
+
+    for entry in old.iter().filter(|e| !kept.contains(e.id.as_str())) {
+        changes.push(change(entry, Some(entry), None, vec![ChangeKind::Removed]));
+    }
+
+    changes
+}
+
+fn change(entry: &HostEntry, before: Option<&HostEntry>, after: Option<&HostEntry>, kinds: Vec<ChangeKind>) -> EntryChange {
+    EntryChange {
+        id: entry.id.clone(),
+        domain: entry.domains.first().cloned().unwrap_or_default(),
+        kinds,
+        before: before.cloned(),
+        after: after.cloned(),
+    }
+}
+
+/// Unified diff of two texts, or an empty string when they are equal
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let ops = diff_ops(&old, &new);
+
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, Op::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+
+    // Changes closer than twice the context share a hunk
+    let mut k = 0;
+    while k < changes.len() {
+        let start = changes[k].saturating_sub(CONTEXT);
+        let mut end = changes[k];
+        while k + 1 < changes.len() && changes[k + 1] <= end + 2 * CONTEXT + 1 {
+            k += 1;
+            end = changes[k];
+        }
+        let end = (end + CONTEXT + 1).min(ops.len());
+        k += 1;
+
+        let hunk = &ops[start..end];
+        let (old_start, new_start) = hunk_start(&ops[..start]);
+        let old_len = hunk.iter().filter(|op| !matches!(op, Op::Insert(_))).count();
+        let new_len = hunk.iter().filter(|op| !matches!(op, Op::Delete(_))).count();
+
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_len),
+            range(new_start, new_len)
+        ));
+        for op in hunk {
+            let (marker, line) = match *op {
+                Op::Equal(i, _) => (' ', old[i]),
+                Op::Delete(i) => ('-', old[i]),
+                Op::Insert(j) => ('+', new[j]),
+            };
+            out.push(marker);
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+/// Old and new line counts before a hunk
+fn hunk_start(before: &[Op]) -> (usize, usize) {
+    let old = before.iter().filter(|op| !matches!(op, Op::Insert(_))).count();
+    let new = before.iter().filter(|op| !matches!(op, Op::Delete(_))).count();
+    (old, new)
+}
+
+/// `start,len` as written in a hunk header (1-based; empty ranges point at
+/// the line before)
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Edit script turning `old` into `new`: deletions come before insertions
+/// within each changed stretch
+fn diff_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+
+    for (o, n) in match_in_order(old, new).into_iter().chain(std::iter::once((old.len(), new.len()))) {
+        ops.extend((i..o).map(Op::Delete));
+        ops.extend((j..n).map(Op::Insert));
+        if o < old.len() && n < new.len() {
+            ops.push(Op::Equal(o, n));
+        }
+        (i, j) = (o + 1, n + 1);
+    }
+
+    ops
+}
+
+/// Largest stretch (old lines times new lines) matched with the quadratic
+/// longest common subsequence
+const LCS_LIMIT: usize = 1 << 18;
+
+/// Order-preserving matching of equal items (patience diff). Common
+/// prefixes and suffixes are matched first, then items that occur exactly
+/// once on both sides anchor the middle, which is matched again between the
+/// anchors. Stretches without such anchors use a longest common subsequence,
+/// or a greedy pass when they are too large for one.
+pub fn match_in_order<K: Eq + Hash>(old: &[K], new: &[K]) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    match_range(old, new, 0..old.len(), 0..new.len(), &mut matches);
+    matches
+}
+
+fn match_range<K: Eq + Hash>(
+    old: &[K],
+    new: &[K],
+    mut a: Range<usize>,
+    mut b: Range<usize>,
+    matches: &mut Vec<(usize, usize)>,
+) {
+    while !a.is_empty() && !b.is_empty() && old[a.start] == new[b.start] {
+        matches.push((a.start, b.start));
+        a.start += 1;
+        b.start += 1;
+    }
+
+    let mut suffix = 0;
+    while !a.is_empty() && !b.is_empty() && old[a.end - 1] == new[b.end - 1] {
+        a.end -= 1;
+        b.end -= 1;
+        suffix += 1;
+    }
+
+    if !a.is_empty() && !b.is_empty() {
+        let anchors = unique_anchors(old, new, a.clone(), b.clone());
+        if !anchors.is_empty() {
+            let (mut i, mut j) = (a.start, b.start);
+            for (o, n) in anchors {
+                match_range(old, new, i..o, j..n, matches);
+                matches.push((o, n));
+                (i, j) = (o + 1, n + 1);
+            }
+            match_range(old, new, i..a.end, j..b.end, matches);
+        } else if a.len() * b.len() <= LCS_LIMIT {
+            match_lcs(old, new, a.clone(), b.clone(), matches);
+        } else {
+            match_greedy(old, new, a.clone(), b.clone(), matches);
+        }
+    }
+
+    matches.extend((0..suffix).map(|k| (a.end + k, b.end + k)));
+}
+
+/// Items that occur once in each range, paired up and reduced to the longest
+/// run that is in order on both sides
+fn unique_anchors<K: Eq + Hash>(old: &[K], new: &[K], a: Range<usize>, b: Range<usize>) -> Vec<(usize, usize)> {
+    // Occurrences in old, occurrences in new, last old index, last new index
+    let mut seen: HashMap<&K, (usize, usize, usize, usize)> = HashMap::new();
+    for i in a {
+        let slot = seen.entry(&old[i]).or_default();
+        slot.0 += 1;
+        slot.2 = i;
+    }
+    for j in b.clone() {
+        if let Some(slot) = seen.get_mut(&new[j]) {
+            slot.1 += 1;
+            slot.3 = j;
+        }
+    }
+
+    let pairs: Vec<(usize, usize)> = b
+        .filter_map(|j| seen.get(&new[j]))
+        .filter(|slot| slot.0 == 1 && slot.1 == 1)
+        .map(|slot| (slot.2, slot.3))
+        .collect();
+
+    // Patience sorting: longest subsequence increasing in the old index
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev = vec![None; pairs.len()];
+    for (k, &(o, _)) in pairs.iter().enumerate() {
+        let pos = tails.partition_point(|&t| pairs[t].0 < o);
+        if pos > 0 {
+            prev[k] = Some(tails[pos - 1]);
+        }
+        if pos == tails.len() {
+            tails.push(k);
+        } else {
+            tails[pos] = k;
+        }
+    }
+
+    let mut anchors = Vec::with_capacity(tails.len());
+    let mut cur = tails.last().copied();
+    while let Some(k) = cur {
+        anchors.push(pairs[k]);
+        cur = prev[k];
+    }
+    anchors.reverse();
+    anchors
+}
+
+/// Exact longest common subsequence of two small ranges
+fn match_lcs<K: Eq>(old: &[K], new: &[K], a: Range<usize>, b: Range<usize>, matches: &mut Vec<(usize, usize)>) {
+    let (n, m) = (a.len(), b.len());
+    // lengths[i * (m + 1) + j]: LCS of old[a.start + i..] and new[b.start + j..]
+    let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i * (m + 1) + j] = if old[a.start + i] == new[b.start + j] {
+                lengths[(i + 1) * (m + 1) + j + 1] + 1
+            } else {
+                lengths[(i + 1) * (m + 1) + j].max(lengths[i * (m + 1) + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[a.start + i] == new[b.start + j] {
+            matches.push((a.start + i, b.start + j));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * (m + 1) + j] >= lengths[i * (m + 1) + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+}
+
+/// Linear matching for large ranges: each new item takes the next equal old
+/// item
+fn match_greedy<K: Eq + Hash>(old: &[K], new: &[K], a: Range<usize>, b: Range<usize>, matches: &mut Vec<(usize, usize)>) {
+    let mut positions: HashMap<&K, VecDeque<usize>> = HashMap::new();
+    for i in a.clone() {
+        positions.entry(&old[i]).or_default().push_back(i);
+    }
+
+    let mut next_old = a.start;
+    for j in b {
+        if let Some(queue) = positions.get_mut(&new[j]) {
+            while queue.front().is_some_and(|&i| i < next_old) {
+                queue.pop_front();
+            }
+            if let Some(i) = queue.pop_front() {
+                matches.push((i, j));
+                next_old = i + 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moved_item_is_left_unmatched() {
+        let old = ["a", "b", "c", "d"];
+        let new = ["d", "a", "b", "c"];
+        assert_eq!(match_in_order(&old, &new), [(0, 1), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn repeated_items_match_in_order() {
+        // `x` repeats, so only `b` anchors the middle
+        let old = ["x", "a", "x", "b", "x"];
+        let new = ["x", "x", "b", "x"];
+        assert_eq!(match_in_order(&old, &new), [(0, 0), (2, 1), (3, 2), (4, 3)]);
+    }
+}
@@ -1,38 +1,42 @@
-use std::net::ToSocketAddrs;
+use crate::error::{AppError, ErrorCode};
+use std::net::{IpAddr, ToSocketAddrs};
 use std::process::Command;
 
-pub fn lookup_domain(domain: &str) -> Result<String, String> {
+pub fn lookup_domain(domain: &str) -> Result<String, AppError> {
+    match resolve_all(domain)?.first() {
+        Some(addr) => Ok(addr.to_string()),
+        None => Err(AppError::new(ErrorCode::DnsLookupFailed, "No IP address found for domain")),
+    }
+}
+
+/// Every address the system resolver returns for a domain,
+/// hosts file included
+pub fn resolve_all(domain: &str) -> Result<Vec<IpAddr>, AppError> {
     // Add a dummy port to satisfy ToSocketAddrs
     let address = format!("{}:0", domain);
 
-    match address.to_socket_addrs() {
-        Ok(mut addrs) => {
-            if let Some(addr) = addrs.next() {
-                Ok(addr.ip().to_string())
-            } else {
-                Err("No IP address found for domain".to_string())
-            }
-        }
-        Err(e) => Err(format!("DNS lookup failed: {}", e)),
-    }
+    let addrs = address.to_socket_addrs().map_err(|e| {
+        AppError::new(ErrorCode::DnsLookupFailed, format!("DNS lookup failed: {}", e)).with_os_error(&e)
+    })?;
+    Ok(addrs.map(|addr| addr.ip()).collect())
 }
 
 /// Best-effort DNS cache flush for the current platform.
 /// This requires elevated privileges on most systems.
-pub fn flush_dns_cache() -> Result<(), String> {
+pub fn flush_dns_cache() -> Result<(), AppError> {
     #[cfg(target_os = "windows")]
     {
         let output = Command::new("ipconfig")
             .arg("/flushdns")
             .output()
-            .map_err(|e| format!("Failed to run ipconfig /flushdns: {}", e))?;
+            .map_err(|e| AppError::command("ipconfig /flushdns", &e))?;
 
         if output.status.success() {
             Ok(())
         } else {
-            Err(format!(
-                "ipconfig /flushdns failed: {}",
-                String::from_utf8_lossy(&output.stderr)
+            Err(AppError::new(
+                ErrorCode::DnsFlushFailed,
+                format!("ipconfig /flushdns failed: {}", String::from_utf8_lossy(&output.stderr)),
             ))
         }
     }
@@ -43,18 +47,18 @@ pub fn flush_dns_cache() -> Result<(), String> {
         let first = Command::new("dscacheutil")
             .arg("-flushcache")
             .status()
-            .map_err(|e| format!("Failed to run dscacheutil -flushcache: {}", e))?;
+            .map_err(|e| AppError::command("dscacheutil -flushcache", &e))?;
 
         let second = Command::new("killall")
             .arg("-HUP")
             .arg("mDNSResponder")
             .status()
-            .map_err(|e| format!("Failed to run killall -HUP mDNSResponder: {}", e))?;
+            .map_err(|e| AppError::command("killall -HUP mDNSResponder", &e))?;
 
         if first.success() && second.success() {
             Ok(())
         } else {
-            Err("Failed to flush DNS cache on macOS".to_string())
+            Err(AppError::new(ErrorCode::DnsFlushFailed, "Failed to flush DNS cache on macOS"))
         }
     }
 
@@ -68,30 +72,35 @@ pub fn flush_dns_cache() -> Result<(), String> {
             ("systemctl", &["restart", "nscd"]),
         ];
 
-        let mut last_error: Option<String> = None;
+        let mut last_error: Option<AppError> = None;
 
         for (cmd, args) in candidates {
             match Command::new(cmd).args(*args).status() {
                 Ok(status) if status.success() => return Ok(()),
                 Ok(status) => {
-                    last_error = Some(format!(
-                        "{} {:?} exited with status {}",
-                        cmd, args, status
+                    last_error = Some(AppError::new(
+                        ErrorCode::CommandFailed,
+                        format!("{} {:?} exited with status {}", cmd, args, status),
                     ));
                 }
                 Err(e) => {
-                    last_error = Some(format!("Failed to run {} {:?}: {}", cmd, args, e));
+                    last_error = Some(AppError::command(&format!("{} {:?}", cmd, args), &e));
                 }
             }
         }
 
-        Err(last_error.unwrap_or_else(|| {
-            "DNS cache flush is not supported or tooling is missing on this system".to_string()
-        }))
+        Err(match last_error {
+            Some(source) => AppError::new(ErrorCode::DnsFlushFailed, format!("Failed to flush DNS cache: {}", source))
+                .with_source(source),
+            None => AppError::new(
+                ErrorCode::DnsFlushFailed,
+                "DNS cache flush is not supported or tooling is missing on this system",
+            ),
+        })
     }
 
     #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
     {
-        Err("DNS cache flush is not supported on this platform".to_string())
+        Err(AppError::new(ErrorCode::Unsupported, "DNS cache flush is not supported on this platform"))
     }
 }
@@ -0,0 +1,1827 @@
+//! Lossless model of a hosts file.
+//!
+//! Every physical line is kept in order together with its original text and
+//! line terminator, so a document that is never edited serializes back
+//! byte-for-byte. Applying new `HostsData` only re-renders the lines whose
+//! entries actually changed; blank lines, comment blocks and section banners
+//! are left exactly as they were.
+//!
+//! A section banner owns every line below it up to the next banner. A
+//! disabled section carries a `[disabled]` marker on its banner and comments
+//! its entries out one level deeper than usual (`# ` for entries that are
+//! on, `## ` for entries that were already off), so turning the section back
+//! on restores each entry to its own state.
+//!
+//! Windows ignores hostnames past the ninth on a line, so entries with more
+//! names than the alias limit are written as several lines with the same
+//! IP. A line filled up to the limit and followed by lines with the same IP
+//! and state reads back as one entry.
+//!
+//! When the file contains a `# BEGIN EasyHosts` / `# END EasyHosts` block,
+//! only the lines inside it are EasyHosts' to edit. Everything outside is
+//! left to other tools and exposed read-only as foreign content.
+
+use crate::annotations;
+use crate::diff::match_in_order;
+use crate::models::{ForeignContent, HostEntry, HostsData, Section};
+use crate::validation;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::ops::Range;
+
+/// Marker appended to the banner of a disabled section
+const SECTION_DISABLED_MARKER: &str = "[disabled]";
+
+/// Names per line before an entry is split; the Windows resolver ignores
+/// the rest
+pub const DEFAULT_MAX_ALIASES: usize = 9;
+
+/// Markers delimiting the region EasyHosts owns in managed mode
+const MANAGED_BEGIN: &str = "# BEGIN EasyHosts";
+const MANAGED_END: &str = "# END EasyHosts";
+
+/// Line terminator of a single physical line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    /// Last line of a file that does not end with a newline
+    None,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::None => "",
+        }
+    }
+}
+
+/// What a line means to EasyHosts
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineKind {
+    Blank,
+    /// Standalone comment, text after the `#`
+    Comment(String),
+    Section(Box<SectionLine>),
+    Entry(Box<EntryLine>),
+    /// Non-comment text that is not a usable entry (e.g. a lone IP)
+    Unknown,
+    /// `# BEGIN EasyHosts`
+    ManagedBegin,
+    /// `# END EasyHosts`
+    ManagedEnd,
+}
+
+/// A single physical line of the hosts file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub kind: LineKind,
+    raw: String,
+    ending: LineEnding,
+}
+
+impl Line {
+    fn blank(ending: LineEnding) -> Self {
+        Line {
+            kind: LineKind::Blank,
+            raw: String::new(),
+            ending,
+        }
+    }
+
+    fn marker(kind: LineKind, text: &str, ending: LineEnding) -> Self {
+        Line {
+            kind,
+            raw: text.to_string(),
+            ending,
+        }
+    }
+
+    /// Original (or re-rendered) text of the line, without its terminator
+    pub fn text(&self) -> &str {
+        &self.raw
+    }
+}
+
+/// A section banner, e.g. `# === Development ===`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionLine {
+    pub id: String,
+    pub title: String,
+    pub enabled: bool,
+    /// Decoration around the title, e.g. `# === ` and ` ===`
+    prefix: String,
+    suffix: String,
+}
+
+impl SectionLine {
+    fn new(section: &Section) -> Self {
+        SectionLine {
+            id: section.id.clone(),
+            title: section.title.clone(),
+            enabled: section.enabled,
+            prefix: "# === ".to_string(),
+            suffix: " ===".to_string(),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = format!("{}{}{}", self.prefix, self.title, self.suffix);
+        if !self.enabled {
+            out.push(' ');
+            out.push_str(SECTION_DISABLED_MARKER);
+        }
+        out
+    }
+
+    fn to_section(&self) -> Section {
+        Section {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            enabled: self.enabled,
+        }
+    }
+}
+
+/// An entry line together with the whitespace it was written with
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryLine {
+    pub enabled: bool,
+    pub ip: String,
+    pub domains: Vec<String>,
+    /// Inline comment after the domains, including any `@key=value`
+    /// annotations
+    pub comment: Option<String>,
+    /// Commented out because its section is disabled
+    section_off: bool,
+    layout: EntryLayout,
+}
+
+/// Whitespace and markers around the tokens of an entry line.
+/// Columns are counted in characters from the start of the IP.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct EntryLayout {
+    indent: String,
+    /// `#`s plus the whitespace after them on a commented-out line
+    disabled_prefix: Option<String>,
+    /// Whitespace before each domain
+    gaps: Vec<String>,
+    /// Column each domain started at
+    domain_cols: Vec<usize>,
+    comment_gap: Option<String>,
+    comment_col: Option<usize>,
+    /// `#` plus the whitespace after it on the inline comment
+    comment_marker: Option<String>,
+    trailing: String,
+}
+
+impl EntryLine {
+    fn new(entry: &HostEntry, layout: EntryLayout, section_off: bool) -> Self {
+        EntryLine {
+            enabled: entry.enabled,
+            ip: entry.ip.clone(),
+            domains: entry.domains.clone(),
+            comment: annotations::render(entry.comment.as_deref(), &entry.tags, &entry.meta),
+            section_off,
+            layout,
+        }
+    }
+
+    /// Number of `#`s the line was commented out with
+    fn depth(&self) -> usize {
+        self.layout
+            .disabled_prefix
+            .as_deref()
+            .map(|prefix| prefix.matches('#').count())
+            .unwrap_or(0)
+    }
+
+    /// Render the line, keeping the original columns where possible
+    fn render(&self) -> String {
+        let layout = &self.layout;
+        let mut out = layout.indent.clone();
+
+        // Outside a disabled section any number of `#`s means disabled, so
+        // the original prefix is kept; inside one the count carries meaning
+        let depth = usize::from(!self.enabled) + usize::from(self.section_off);
+        if depth > 0 {
+            match &layout.disabled_prefix {
+                Some(prefix) if self.depth() == depth || (!self.section_off && self.depth() > 0) => {
+                    out.push_str(prefix)
+                }
+                _ => {
+                    out.push_str(&"#".repeat(depth));
+                    out.push(' ');
+                }
+            }
+        }
+
+        // Columns are relative to the start of the IP
+        let base = out.chars().count();
+        out.push_str(&self.ip);
+
+        for (i, domain) in self.domains.iter().enumerate() {
+            let gap = layout.gaps.get(i).map(String::as_str);
+            let col = layout.domain_cols.get(i).copied();
+            push_gap(&mut out, base, gap, col);
+            out.push_str(domain);
+        }
+
+        if let Some(comment) = &self.comment {
+            push_gap(&mut out, base, layout.comment_gap.as_deref(), layout.comment_col);
+            out.push_str(layout.comment_marker.as_deref().unwrap_or("# "));
+            out.push_str(comment);
+        }
+
+        out.push_str(&layout.trailing);
+        out
+    }
+
+    /// Split into lines of at most `max` names, all with the same IP.
+    /// The inline comment stays on the first line; `0` means no limit.
+    fn split(&self, max: usize) -> Vec<EntryLine> {
+        if max == 0 || self.domains.len() <= max {
+            return vec![self.clone()];
+        }
+
+        let continuation = EntryLayout {
+            comment_gap: None,
+            comment_col: None,
+            comment_marker: None,
+            trailing: String::new(),
+            ..self.layout.clone()
+        };
+
+        self.domains
+            .chunks(max)
+            .enumerate()
+            .map(|(i, chunk)| match i {
+                0 => EntryLine {
+                    domains: chunk.to_vec(),
+                    ..self.clone()
+                },
+                _ => EntryLine {
+                    domains: chunk.to_vec(),
+                    comment: None,
+                    layout: continuation.clone(),
+                    ..self.clone()
+                },
+            })
+            .collect()
+    }
+
+    /// Whether the line is in effect (not commented out at all)
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.section_off
+    }
+
+    /// Character column of the IP within the line as parsed
+    pub fn ip_column(&self) -> usize {
+        let prefix = self.layout.disabled_prefix.as_deref().unwrap_or("");
+        self.layout.indent.chars().count() + prefix.chars().count()
+    }
+
+    /// Character columns of the domains within the line as parsed
+    pub fn domain_columns(&self) -> Vec<usize> {
+        let base = self.ip_column();
+        self.layout.domain_cols.iter().map(|col| base + col).collect()
+    }
+
+    /// Render this line with a different list of domains
+    pub fn render_with_domains(&self, domains: &[String]) -> String {
+        EntryLine {
+            domains: domains.to_vec(),
+            ..self.clone()
+        }
+        .render()
+    }
+
+    fn to_entry(&self) -> HostEntry {
+        let annotated = annotations::parse(self.comment.as_deref());
+        HostEntry {
+            id: String::new(),
+            enabled: self.enabled,
+            ip: self.ip.clone(),
+            domains: self.domains.clone(),
+            comment: annotated.comment,
+            section: None,
+            tags: annotated.tags,
+            meta: annotated.meta,
+        }
+    }
+}
+
+/// Separate two tokens, padding up to the original column when the original
+/// separator was made of spaces. Tab separators are reused as-is.
+fn push_gap(out: &mut String, base: usize, gap: Option<&str>, col: Option<usize>) {
+    match (gap, col) {
+        (Some(gap), _) if gap.contains('\t') => out.push_str(gap),
+        (Some(_), Some(col)) => {
+            let current = out.chars().count() - base;
+            let pad = col.saturating_sub(current).max(1);
+            out.push_str(&" ".repeat(pad));
+        }
+        _ => out.push(' '),
+    }
+}
+
+/// A hosts file as an ordered list of lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostsDocument {
+    lines: Vec<Line>,
+    /// Terminator used for lines added by edits
+    newline: LineEnding,
+    /// Names per line before an entry is split
+    max_aliases: usize,
+}
+
+impl HostsDocument {
+    /// Parse hosts file content without losing anything
+    pub fn parse(content: &str) -> Self {
+        let mut lines = Vec::new();
+        let mut crlf = 0;
+        let mut lf = 0;
+
+        let mut section_ids = HashSet::new();
+        let mut section_off = false;
+
+        let mut rest = content;
+        while !rest.is_empty() {
+            let (raw, ending, next) = match rest.find('\n') {
+                Some(pos) if rest[..pos].ends_with('\r') => {
+                    crlf += 1;
+                    (&rest[..pos - 1], LineEnding::CrLf, &rest[pos + 1..])
+                }
+                Some(pos) => {
+                    lf += 1;
+                    (&rest[..pos], LineEnding::Lf, &rest[pos + 1..])
+                }
+                None => (rest, LineEnding::None, ""),
+            };
+
+            let mut kind = classify_line(raw);
+            match &mut kind {
+                LineKind::Section(section) => {
+                    section.id = unique_id(&section.title, "section", &mut section_ids);
+                    section_off = !section.enabled;
+                }
+                // One level of commenting belongs to the section
+                LineKind::Entry(entry) if section_off => {
+                    entry.section_off = true;
+                    entry.enabled = entry.depth() <= 1;
+                }
+                _ => {}
+            }
+
+            lines.push(Line {
+                kind,
+                raw: raw.to_string(),
+                ending,
+            });
+            rest = next;
+        }
+
+        let newline = if crlf > lf { LineEnding::CrLf } else { LineEnding::Lf };
+
+        HostsDocument {
+            lines,
+            newline,
+            max_aliases: DEFAULT_MAX_ALIASES,
+        }
+    }
+
+    /// Use a different alias limit for reading and writing entries.
+    /// `0` keeps every entry on a single line.
+    pub fn with_max_aliases(mut self, max_aliases: usize) -> Self {
+        self.max_aliases = max_aliases;
+        self
+    }
+
+    /// Render structured data as a fresh document
+    pub fn from_hosts_data(data: &HostsData, max_aliases: usize) -> Self {
+        let mut document = HostsDocument::parse("").with_max_aliases(max_aliases);
+        document.apply(data);
+        document
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Terminator used by most lines of the document
+    pub fn newline(&self) -> LineEnding {
+        self.newline
+    }
+
+    /// Terminate every line with `newline`. A missing newline at the end
+    /// of the file stays missing.
+    pub fn convert_line_endings(&mut self, newline: LineEnding) {
+        for line in &mut self.lines {
+            if line.ending != LineEnding::None {
+                line.ending = newline;
+            }
+        }
+        self.newline = newline;
+    }
+
+    /// Line indices of the `BEGIN` and `END` markers of the managed block
+    pub fn managed_range(&self) -> Option<(usize, usize)> {
+        let begin = self.lines.iter().position(|l| l.kind == LineKind::ManagedBegin)?;
+        let end = self.lines[begin + 1..]
+            .iter()
+            .position(|l| l.kind == LineKind::ManagedEnd)?;
+        Some((begin, begin + 1 + end))
+    }
+
+    /// Structured view of the document for the UI. In managed mode only
+    /// the managed block is editable; the rest is reported as foreign.
+    pub fn to_hosts_data(&self) -> HostsData {
+        let max = self.max_aliases;
+        let Some((begin, end)) = self.managed_range() else {
+            let mut data = hosts_data_from(&self.lines, max);
+            assign_entry_ids(&mut data.entries);
+            return data;
+        };
+
+        let before = &self.lines[..begin];
+        let after = &self.lines[end + 1..];
+        let mut entries = hosts_data_from(before, max).entries;
+        entries.extend(hosts_data_from(after, max).entries);
+        for entry in &mut entries {
+            entry.section = None;
+        }
+
+        let mut data = HostsData {
+            foreign: Some(ForeignContent {
+                before: render_lines(before),
+                after: render_lines(after),
+                entries,
+            }),
+            ..hosts_data_from(&self.lines[begin + 1..end], max)
+        };
+        if let Some(foreign) = &mut data.foreign {
+            assign_entry_ids(data.entries.iter_mut().chain(&mut foreign.entries));
+        }
+        data
+    }
+
+    /// Apply edited data to the document, touching only changed lines.
+    /// Unchanged entries keep their original text, new entries are placed
+    /// after their predecessor and removed entries drop their line.
+    /// Sections are reordered, renamed, toggled or dropped as whole blocks.
+    /// In managed mode nothing outside the managed block is touched.
+    pub fn apply(&mut self, data: &HostsData) {
+        match self.managed_range() {
+            Some((begin, end)) => {
+                let region: Vec<Line> = self.lines.drain(begin + 1..end).collect();
+                let updated = apply_lines(region, data, self.newline, self.max_aliases);
+                self.lines.splice(begin + 1..begin + 1, updated);
+            }
+            None => {
+                let lines = std::mem::take(&mut self.lines);
+                self.lines = apply_lines(lines, data, self.newline, self.max_aliases);
+            }
+        }
+    }
+
+    /// Lines EasyHosts may rewrite: the managed block, or the whole file
+    fn owned_range(&self) -> Range<usize> {
+        match self.managed_range() {
+            Some((begin, end)) => begin + 1..end,
+            None => 0..self.lines.len(),
+        }
+    }
+
+    /// Sort the entries of each section by hostname, then IP. An entry
+    /// moves together with its comment line and continuation lines; blank
+    /// lines, other comments and section banners keep their places, and
+    /// entries on either side of them are sorted together.
+    pub fn sort_entries(&mut self) {
+        let owned = self.owned_range();
+        let mut start = owned.start;
+        for idx in owned.clone() {
+            if matches!(self.lines[idx].kind, LineKind::Section(_)) {
+                self.sort_section(start..idx);
+                start = idx + 1;
+            }
+        }
+        self.sort_section(start..owned.end);
+    }
+
+    /// Sort the entries in `range`, the lines between two section banners
+    fn sort_section(&mut self, range: Range<usize>) {
+        // Each item is an entry with its attached lines, or one line that
+        // stays in place
+        let mut items: Vec<(bool, Range<usize>)> = Vec::new();
+        let mut idx = range.start;
+        while idx < range.end {
+            match self.unit_end(idx, range.end) {
+                Some(end) => {
+                    items.push((true, idx..end));
+                    idx = end;
+                }
+                None => {
+                    items.push((false, idx..idx + 1));
+                    idx += 1;
+                }
+            }
+        }
+        if items.iter().filter(|(unit, _)| *unit).count() < 2 {
+            return;
+        }
+
+        let mut units: Vec<Vec<Line>> = items
+            .iter()
+            .filter(|(unit, _)| *unit)
+            .map(|(_, unit)| self.lines[unit.clone()].to_vec())
+            .collect();
+        units.sort_by_cached_key(|unit| {
+            unit.iter()
+                .find_map(|line| match &line.kind {
+                    LineKind::Entry(entry) => {
+                        let first = entry.domains.first().map(|d| d.to_lowercase());
+                        Some((first, entry.ip.clone()))
+                    }
+                    _ => None,
+                })
+                .unwrap_or_default()
+        });
+
+        let last_ending = self.lines[range.end - 1].ending;
+        let mut units = units.into_iter();
+        let mut sorted: Vec<Line> = Vec::with_capacity(range.len());
+        for (unit, lines) in items {
+            if unit {
+                sorted.extend(units.next().unwrap_or_default());
+            } else {
+                sorted.extend_from_slice(&self.lines[lines]);
+            }
+        }
+        for line in &mut sorted {
+            if line.ending == LineEnding::None {
+                line.ending = self.newline;
+            }
+        }
+        if let Some(line) = sorted.last_mut() {
+            line.ending = last_ending;
+        }
+        self.lines.splice(range, sorted);
+    }
+
+    /// End of the entry starting at `idx` (with the comment line above it
+    /// and its continuation lines), if an entry starts there
+    fn unit_end(&self, idx: usize, limit: usize) -> Option<usize> {
+        let head = match &self.lines[idx].kind {
+            LineKind::Entry(_) => idx,
+            _ if comment_text(&self.lines[idx]).is_some()
+                && idx + 1 < limit
+                && matches!(self.lines[idx + 1].kind, LineKind::Entry(_)) =>
+            {
+                idx + 1
+            }
+            _ => return None,
+        };
+
+        let mut end = head + 1;
+        while end < limit && continues(&self.lines, end, self.max_aliases) {
+            end += 1;
+        }
+        Some(end)
+    }
+
+    /// Line up the entries of each section in columns using spaces: every
+    /// hostname position, and the inline comments, start at the same column
+    /// on all lines. Commented-out entries get a plain `# ` prefix.
+    pub fn align_columns(&mut self) {
+        let owned = self.owned_range();
+        let mut block: Vec<usize> = Vec::new();
+
+        for idx in owned.clone() {
+            match &self.lines[idx].kind {
+                LineKind::Section(_) => self.align_block(&std::mem::take(&mut block)),
+                LineKind::Entry(_) => block.push(idx),
+                _ => {}
+            }
+        }
+        self.align_block(&block);
+    }
+
+    fn align_block(&mut self, block: &[usize]) {
+        let entries: Vec<&EntryLine> = block
+            .iter()
+            .filter_map(|&idx| match &self.lines[idx].kind {
+                LineKind::Entry(entry) => Some(&**entry),
+                _ => None,
+            })
+            .collect();
+
+        let prefix_len = |entry: &EntryLine| {
+            let depth = usize::from(!entry.enabled) + usize::from(entry.section_off);
+            if depth > 0 { depth + 1 } else { 0 }
+        };
+
+        // Absolute column of each hostname position
+        let mut columns = Vec::new();
+        let mut next = entries.iter().map(|e| prefix_len(e) + e.ip.chars().count()).max().unwrap_or(0) + 1;
+        let positions = entries.iter().map(|e| e.domains.len()).max().unwrap_or(0);
+        for k in 0..positions {
+            columns.push(next);
+            let width = entries
+                .iter()
+                .filter_map(|e| e.domains.get(k))
+                .map(|d| d.chars().count())
+                .max()
+                .unwrap_or(0);
+            next += width + 1;
+        }
+
+        let comment_col = entries
+            .iter()
+            .filter(|e| e.comment.is_some() && !e.domains.is_empty())
+            .map(|e| columns[e.domains.len() - 1] + e.domains[e.domains.len() - 1].chars().count() + 1)
+            .max();
+
+        for &idx in block {
+            let LineKind::Entry(entry) = &mut self.lines[idx].kind else {
+                continue;
+            };
+
+            let prefix = prefix_len(entry);
+            let depth = prefix.saturating_sub(1);
+            entry.layout = EntryLayout {
+                indent: String::new(),
+                disabled_prefix: Some(format!("{} ", "#".repeat(depth))).filter(|_| depth > 0),
+                gaps: vec![" ".to_string(); entry.domains.len()],
+                domain_cols: columns.iter().take(entry.domains.len()).map(|c| c - prefix).collect(),
+                comment_gap: Some(" ".to_string()),
+                comment_col: comment_col.map(|c| c - prefix),
+                comment_marker: Some("# ".to_string()),
+                trailing: String::new(),
+            };
+            let raw = entry.render();
+            self.lines[idx].raw = raw;
+        }
+    }
+
+    /// Switch the document to managed mode by adding the block markers.
+    /// With `adopt_existing`, everything from the first active entry or
+    /// section onwards moves into the block; otherwise an empty block is
+    /// appended and the existing content becomes foreign.
+    /// Returns false if the document already has a managed block.
+    pub fn enable_managed_region(&mut self, adopt_existing: bool) -> bool {
+        if self.managed_range().is_some() {
+            return false;
+        }
+
+        let first_owned = self.lines.iter().position(|l| match &l.kind {
+            LineKind::Section(_) => true,
+            LineKind::Entry(entry) => entry.enabled,
+            _ => false,
+        });
+        let begin = match first_owned {
+            Some(idx) if adopt_existing => attached_comment(&self.lines, idx).unwrap_or(idx),
+            _ => self.lines.len(),
+        };
+
+        if let Some(last) = self.lines.last_mut() {
+            if last.ending == LineEnding::None {
+                last.ending = self.newline;
+            }
+        }
+
+        self.lines.push(Line::marker(LineKind::ManagedEnd, MANAGED_END, self.newline));
+        self.lines.insert(begin, Line::marker(LineKind::ManagedBegin, MANAGED_BEGIN, self.newline));
+        if begin > 0 && self.lines[begin - 1].kind != LineKind::Blank {
+            self.lines.insert(begin, Line::blank(self.newline));
+        }
+
+        true
+    }
+}
+
+impl fmt::Display for HostsDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&render_lines(&self.lines))
+    }
+}
+
+fn render_lines(lines: &[Line]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&line.raw);
+        out.push_str(line.ending.as_str());
+    }
+    out
+}
+
+/// Entries and sections found in a run of lines
+fn hosts_data_from(lines: &[Line], max_aliases: usize) -> HostsData {
+    let mut entries: Vec<HostEntry> = Vec::new();
+    let mut sections = Vec::new();
+    let mut current: Option<&str> = None;
+
+    for (idx, line) in lines.iter().enumerate() {
+        match &line.kind {
+            LineKind::Section(section) => {
+                sections.push(section.to_section());
+                current = Some(&section.id);
+            }
+            LineKind::Entry(entry) => match entries.last_mut() {
+                Some(last) if continues(lines, idx, max_aliases) => {
+                    last.domains.extend(entry.domains.iter().cloned());
+                }
+                _ => entries.push(entry_view(lines, idx, entry, current)),
+            },
+            _ => {}
+        }
+    }
+
+    HostsData {
+        entries,
+        sections,
+        foreign: None,
+        format: None,
+        lossy_lines: Vec::new(),
+        revision: None,
+    }
+}
+
+/// Give every entry an id derived from its first hostname, unique within
+/// the document
+fn assign_entry_ids<'a>(entries: impl IntoIterator<Item = &'a mut HostEntry>) {
+    let mut used = HashSet::new();
+    for entry in entries {
+        let name = entry.domains.first().map_or("", String::as_str);
+        entry.id = unique_id(name, "entry", &mut used);
+    }
+}
+
+/// Give the entries of a later version of a file the ids they had in an
+/// earlier one. Ids depend on hostnames and on the entries before them, so
+/// an entry can be named differently each time the file is read; entries
+/// are matched by content instead, the same way `apply` matches them.
+/// Entries without a match get an id the earlier version does not use.
+pub fn carry_entry_ids(earlier: &[HostEntry], later: &mut [HostEntry]) {
+    let without_id = |e: &HostEntry| HostEntry {
+        id: String::new(),
+        ..e.clone()
+    };
+    let old: Vec<HostEntry> = earlier.iter().map(without_id).collect();
+    let new: Vec<HostEntry> = later.iter().map(without_id).collect();
+
+    // Matched entries, and leftovers between them paired positionally
+    let mut carried: Vec<Option<&str>> = vec![None; later.len()];
+    let matches = match_entries(&old.iter().collect::<Vec<_>>(), &new.iter().collect::<Vec<_>>());
+    let mut start = (0, 0);
+    for (o, n) in matches.into_iter().chain(std::iter::once((old.len(), new.len()))) {
+        for k in 0..(o - start.0).min(n - start.1) {
+            carried[start.1 + k] = Some(earlier[start.0 + k].id.as_str());
+        }
+        if n < new.len() {
+            carried[n] = Some(earlier[o].id.as_str());
+        }
+        start = (o + 1, n + 1);
+    }
+
+    let mut used: HashSet<String> = earlier.iter().map(|e| e.id.clone()).collect();
+    for (entry, id) in later.iter_mut().zip(carried) {
+        entry.id = match id {
+            Some(id) => id.to_string(),
+            None => unique_id(entry.domains.first().map_or("", String::as_str), "entry", &mut used),
+        };
+    }
+}
+
+/// Entry as shown in the UI: a comment line directly above an entry
+/// is treated as that entry's comment. Tags and metadata always come from
+/// the entry line itself.
+fn entry_view(lines: &[Line], idx: usize, entry: &EntryLine, section: Option<&str>) -> HostEntry {
+    let mut host_entry = entry.to_entry();
+    if let Some(text) = attached_comment(lines, idx).and_then(|i| comment_text(&lines[i])) {
+        host_entry.comment = Some(text.to_string());
+    }
+    host_entry.section = section.map(str::to_string);
+    host_entry
+}
+
+/// Whether the entry line at `idx` continues the entry above it: the line
+/// above is filled up to the alias limit, has the same IP and state, and
+/// this line has no comment of its own
+fn continues(lines: &[Line], idx: usize, max_aliases: usize) -> bool {
+    let Some(prev) = idx.checked_sub(1) else {
+        return false;
+    };
+    let (LineKind::Entry(prev), LineKind::Entry(entry)) = (&lines[prev].kind, &lines[idx].kind) else {
+        return false;
+    };
+
+    max_aliases > 0
+        && prev.domains.len() == max_aliases
+        && prev.ip == entry.ip
+        && prev.enabled == entry.enabled
+        && prev.section_off == entry.section_off
+        && entry.comment.is_none()
+}
+
+/// Index of the comment line describing the entry at `idx`, if any
+fn attached_comment(lines: &[Line], idx: usize) -> Option<usize> {
+    let prev = idx.checked_sub(1)?;
+    comment_text(&lines[prev]).map(|_| prev)
+}
+
+/// The preamble before the first section banner, or a banner together
+/// with the lines under it
+struct Block {
+    header: Option<Line>,
+    lines: Vec<Line>,
+}
+
+impl Block {
+    fn section(&self) -> Option<&SectionLine> {
+        match &self.header.as_ref()?.kind {
+            LineKind::Section(section) => Some(section),
+            _ => None,
+        }
+    }
+}
+
+fn split_blocks(lines: Vec<Line>) -> Vec<Block> {
+    let mut blocks = vec![Block {
+        header: None,
+        lines: Vec::new(),
+    }];
+
+    for line in lines {
+        if let LineKind::Section(_) = line.kind {
+            blocks.push(Block {
+                header: Some(line),
+                lines: Vec::new(),
+            });
+        } else if let Some(block) = blocks.last_mut() {
+            block.lines.push(line);
+        }
+    }
+
+    blocks
+}
+
+/// Apply `data` to a run of lines: lay out the sections in the requested
+/// order, then diff each section's entries against its block
+fn apply_lines(lines: Vec<Line>, data: &HostsData, newline: LineEnding, max_aliases: usize) -> Vec<Line> {
+    let mut blocks = split_blocks(lines).into_iter();
+    let preamble = blocks.next().map(|block| block.lines).unwrap_or_default();
+
+    let mut existing: HashMap<String, Block> = HashMap::new();
+    for block in blocks {
+        if let Some(id) = block.section().map(|s| s.id.clone()) {
+            existing.insert(id, block);
+        }
+    }
+
+    // Ids only name entries for the UI; lines are matched by content
+    let entries: Vec<HostEntry> = data
+        .entries
+        .iter()
+        .map(|e| HostEntry {
+            id: String::new(),
+            ..e.clone()
+        })
+        .collect();
+
+    // Entries pointing at a section that no longer exists stay unsectioned
+    let known: HashSet<&str> = data
+        .sections
+        .iter()
+        .map(|s| s.id.as_str())
+        .filter(|id| !id.is_empty())
+        .collect();
+    let unsectioned: Vec<&HostEntry> = entries
+        .iter()
+        .filter(|e| e.section.as_deref().is_none_or(|id| !known.contains(id)))
+        .collect();
+
+    let mut out = apply_entries(preamble, &unsectioned, None, false, newline, max_aliases);
+
+    let mut placed = HashSet::new();
+    for section in &data.sections {
+        // A repeated id refers to the section already placed
+        if !section.id.is_empty() && !placed.insert(section.id.as_str()) {
+            continue;
+        }
+
+        let section_entries: Vec<&HostEntry> = entries
+            .iter()
+            .filter(|e| !section.id.is_empty() && e.section.as_deref() == Some(section.id.as_str()))
+            .collect();
+
+        let (header, body) = match existing.remove(&section.id) {
+            Some(Block { header: Some(header), lines }) => (rewrite_header(header, section), lines),
+            _ => {
+                // Keep new sections visually apart from what precedes them
+                if out.last().is_some_and(|l| l.kind != LineKind::Blank) {
+                    out.push(Line::blank(newline));
+                }
+                let line = SectionLine::new(section);
+                let header = Line {
+                    raw: line.render(),
+                    kind: LineKind::Section(Box::new(line)),
+                    ending: newline,
+                };
+                (header, Vec::new())
+            }
+        };
+
+        out.push(header);
+        out.extend(apply_entries(
+            body,
+            &section_entries,
+            Some(&section.id),
+            !section.enabled,
+            newline,
+            max_aliases,
+        ));
+    }
+
+    // Reordering may move the unterminated last line into the middle
+    let count = out.len();
+    for line in out.iter_mut().take(count.saturating_sub(1)) {
+        if line.ending == LineEnding::None {
+            line.ending = newline;
+        }
+    }
+
+    out
+}
+
+/// Update a banner for a renamed or toggled section, keeping its decoration
+fn rewrite_header(header: Line, section: &Section) -> Line {
+    let LineKind::Section(old) = &header.kind else {
+        return header;
+    };
+
+    let line = SectionLine {
+        id: section.id.clone(),
+        title: section.title.clone(),
+        enabled: section.enabled,
+        ..(**old).clone()
+    };
+    if line == **old {
+        return header;
+    }
+
+    Line {
+        raw: line.render(),
+        kind: LineKind::Section(Box::new(line)),
+        ending: header.ending,
+    }
+}
+
+#[derive(Debug, Clone)]
+enum LineAction {
+    Keep,
+    Drop,
+    Replace(LineKind, String),
+}
+
+/// Diff the entries of one block against `new_entries`
+fn apply_entries(
+    lines: Vec<Line>,
+    new_entries: &[&HostEntry],
+    section: Option<&str>,
+    section_off: bool,
+    newline: LineEnding,
+    max_aliases: usize,
+) -> Vec<Line> {
+    // Each old entry with the lines it is written on
+    let mut old: Vec<(Vec<usize>, HostEntry)> = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let LineKind::Entry(entry) = &line.kind else {
+            continue;
+        };
+        match old.last_mut() {
+            Some((group, last)) if continues(&lines, idx, max_aliases) => {
+                group.push(idx);
+                last.domains.extend(entry.domains.iter().cloned());
+            }
+            _ => old.push((vec![idx], entry_view(&lines, idx, entry, section))),
+        }
+    }
+
+    let old_entries: Vec<&HostEntry> = old.iter().map(|(_, e)| e).collect();
+    let matches = match_entries(&old_entries, new_entries);
+
+    let mut plan = Plan {
+        lines: &lines,
+        actions: vec![LineAction::Keep; lines.len()],
+        inserts_after: HashMap::new(),
+        section_off,
+        max_aliases,
+        newline,
+    };
+    let mut inserts_before_first: Vec<Line> = Vec::new();
+
+    // The stretches between matched entries, each with the match after it
+    let mut gaps = Vec::with_capacity(matches.len() + 1);
+    let mut prev: Option<(usize, usize)> = None;
+    for anchor in matches.iter().copied().map(Some).chain(std::iter::once(None)) {
+        let old_start = prev.map(|(o, _)| o + 1).unwrap_or(0);
+        let new_start = prev.map(|(_, n)| n + 1).unwrap_or(0);
+        let (old_end, new_end) = anchor.unwrap_or((old.len(), new_entries.len()));
+        gaps.push((prev, old_start..old_end, new_start..new_end, anchor));
+        prev = anchor;
+    }
+
+    // An entry that is removed in one place and added unchanged in another
+    // only moved; it keeps its original lines and comment
+    let mut removed: HashMap<&HostEntry, VecDeque<usize>> = HashMap::new();
+    let mut added = Vec::new();
+    for (_, old_gap, new_gap, _) in &gaps {
+        let paired = old_gap.len().min(new_gap.len());
+        for (o, (_, entry)) in old.iter().enumerate().take(old_gap.end).skip(old_gap.start + paired) {
+            removed.entry(entry).or_default().push_back(o);
+        }
+        added.extend(new_gap.start + paired..new_gap.end);
+    }
+    let moved: HashMap<usize, usize> = added
+        .into_iter()
+        .filter_map(|n| Some((n, removed.get_mut(new_entries[n])?.pop_front()?)))
+        .collect();
+
+    // Walk the gaps; pair leftovers positionally as modifications, then
+    // delete or insert whatever remains.
+    for (prev, old_gap, new_gap, anchor) in gaps {
+        let paired = old_gap.len().min(new_gap.len());
+
+        for k in 0..paired {
+            let (group, before) = &old[old_gap.start + k];
+            plan.replace(group, before, new_entries[new_gap.start + k]);
+        }
+        for (group, _) in &old[old_gap.start + paired..old_gap.end] {
+            plan.delete(group);
+        }
+
+        // New entries follow the last entry before them
+        let last_old = if paired > 0 {
+            Some(old_gap.start + paired - 1)
+        } else {
+            prev.map(|(o, _)| o)
+        };
+        let after_line = last_old.and_then(|o| old[o].0.last().copied());
+        for (n, entry) in new_entries.iter().enumerate().take(new_gap.end).skip(new_gap.start + paired) {
+            let added = match moved.get(&n) {
+                Some(&o) => plan.moved_lines(&old[o].0),
+                None => plan.new_lines(after_line, entry),
+            };
+            match after_line {
+                Some(idx) => plan.inserts_after.entry(idx).or_default().extend(added),
+                None => inserts_before_first.extend(added),
+            }
+        }
+
+        // Anchors are either unchanged or edited in place
+        if let Some((o, n)) = anchor {
+            let (group, before) = &old[o];
+            plan.replace(group, before, new_entries[n]);
+        }
+    }
+
+    // Without entries to anchor to, new ones go after the last non-blank
+    // line, kept apart from a trailing comment so they don't adopt it
+    let insert_first_at = match old.first() {
+        Some((group, _)) => attached_comment(&lines, group[0]).unwrap_or(group[0]),
+        None => {
+            let at = lines.iter().rposition(|l| l.kind != LineKind::Blank).map_or(0, |i| i + 1);
+            if at > 0 && !inserts_before_first.is_empty() && comment_text(&lines[at - 1]).is_some() {
+                inserts_before_first.insert(0, Line::blank(newline));
+            }
+            at
+        }
+    };
+
+    let Plan {
+        actions,
+        mut inserts_after,
+        ..
+    } = plan;
+
+    let line_count = lines.len();
+    let mut out = Vec::with_capacity(line_count + inserts_before_first.len());
+
+    for (idx, (line, action)) in lines.into_iter().zip(actions).enumerate() {
+        if idx == insert_first_at {
+            out.append(&mut inserts_before_first);
+        }
+        match action {
+            LineAction::Keep => out.push(line),
+            LineAction::Drop => {}
+            LineAction::Replace(kind, raw) => out.push(Line { kind, raw, ending: line.ending }),
+        }
+        if let Some(mut added) = inserts_after.remove(&idx) {
+            out.append(&mut added);
+        }
+    }
+
+    if insert_first_at >= line_count {
+        out.append(&mut inserts_before_first);
+    }
+
+    out
+}
+
+/// Line edits planned for one block
+struct Plan<'a> {
+    lines: &'a [Line],
+    actions: Vec<LineAction>,
+    /// New lines keyed by the line they follow
+    inserts_after: HashMap<usize, Vec<Line>>,
+    section_off: bool,
+    max_aliases: usize,
+    newline: LineEnding,
+}
+
+impl Plan<'_> {
+    /// Rewrite the lines of an entry in place. Continuation lines are
+    /// reused as long as the entry still needs them; extra ones are added
+    /// after the last line or dropped.
+    fn replace(&mut self, group: &[usize], before: &HostEntry, after: &HostEntry) {
+        let lines = self.lines;
+        let head = group[0];
+        let LineKind::Entry(old_head) = &lines[head].kind else {
+            return;
+        };
+
+        let mut line = EntryLine::new(after, old_head.layout.clone(), self.section_off);
+        if let Some(comment_idx) = attached_comment(lines, head) {
+            // The comment lives on the line above; keep it there and only
+            // update the annotations on the entry line
+            let inline = annotations::parse(old_head.comment.as_deref());
+            line.comment = annotations::render(inline.comment.as_deref(), &after.tags, &after.meta);
+            if before.comment != after.comment {
+                self.actions[comment_idx] = match &after.comment {
+                    Some(text) => {
+                        let raw = rewrite_comment(&lines[comment_idx], text);
+                        LineAction::Replace(LineKind::Comment(text.clone()), raw)
+                    }
+                    None => LineAction::Drop,
+                };
+            }
+        }
+
+        let parts = line.split(self.max_aliases);
+        let used = parts.len();
+        let last = group[group.len() - 1];
+        for (k, mut part) in parts.into_iter().enumerate() {
+            let Some(&line_idx) = group.get(k) else {
+                let added = self.line(part);
+                self.inserts_after.entry(last).or_default().push(added);
+                continue;
+            };
+
+            let LineKind::Entry(old_line) = &lines[line_idx].kind else {
+                continue;
+            };
+            if k > 0 {
+                part.layout = old_line.layout.clone();
+            }
+            if part != **old_line {
+                let raw = part.render();
+                self.actions[line_idx] = LineAction::Replace(LineKind::Entry(Box::new(part)), raw);
+            }
+        }
+
+        for &line_idx in group.iter().skip(used) {
+            self.actions[line_idx] = LineAction::Drop;
+        }
+    }
+
+    fn delete(&mut self, group: &[usize]) {
+        for &line_idx in group {
+            self.actions[line_idx] = LineAction::Drop;
+        }
+        if let Some(comment_idx) = attached_comment(self.lines, group[0]) {
+            self.actions[comment_idx] = LineAction::Drop;
+        }
+    }
+
+    /// Copies of the lines an entry is written on, with the comment above
+    /// it, for moving them elsewhere unchanged
+    fn moved_lines(&self, group: &[usize]) -> Vec<Line> {
+        attached_comment(self.lines, group[0])
+            .into_iter()
+            .chain(group.iter().copied())
+            .map(|idx| {
+                let mut line = self.lines[idx].clone();
+                if line.ending == LineEnding::None {
+                    line.ending = self.newline;
+                }
+                line
+            })
+            .collect()
+    }
+
+    /// Lines for a new entry, borrowing the column layout of a
+    /// neighbouring entry so they line up with the rest of the block
+    fn new_lines(&self, neighbour: Option<usize>, entry: &HostEntry) -> Vec<Line> {
+        let layout = neighbour
+            .and_then(|idx| match &self.lines[idx].kind {
+                LineKind::Entry(line) => Some(EntryLayout {
+                    disabled_prefix: None,
+                    comment_marker: None,
+                    trailing: String::new(),
+                    ..line.layout.clone()
+                }),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        EntryLine::new(entry, layout, self.section_off)
+            .split(self.max_aliases)
+            .into_iter()
+            .map(|line| self.line(line))
+            .collect()
+    }
+
+    fn line(&self, entry: EntryLine) -> Line {
+        Line {
+            raw: entry.render(),
+            kind: LineKind::Entry(Box::new(entry)),
+            ending: self.newline,
+        }
+    }
+}
+
+/// Text of a non-empty standalone comment line
+fn comment_text(line: &Line) -> Option<&str> {
+    match &line.kind {
+        LineKind::Comment(text) if !text.is_empty() => Some(text),
+        _ => None,
+    }
+}
+
+/// Replace the text of a comment line, keeping its indentation and marker
+fn rewrite_comment(line: &Line, text: &str) -> String {
+    let raw = &line.raw;
+    let hash = raw.find('#').unwrap_or(0);
+    let after = &raw[hash + 1..];
+    let marker_len = after.len() - after.trim_start().len();
+    let marker = if marker_len == 0 { " " } else { &after[..marker_len] };
+    format!("{}#{}{}", &raw[..hash], marker, text)
+}
+
+/// Match entries between the old and new lists, in order. Unchanged
+/// entries are matched first; inside the remaining gaps, entries that still
+/// share their address and first domain, and then those sharing just their
+/// first domain, are paired so an edited entry keeps its line.
+fn match_entries(old: &[&HostEntry], new: &[&HostEntry]) -> Vec<(usize, usize)> {
+    match_entries_from(old, new, 0)
+}
+
+/// `match_entries` starting at the given level of likeness
+fn match_entries_from(old: &[&HostEntry], new: &[&HostEntry], level: usize) -> Vec<(usize, usize)> {
+    let found = match level {
+        0 => match_in_order(old, new),
+        1 => {
+            let old_keys: Vec<_> = old.iter().map(|e| (e.ip.as_str(), e.domains.first())).collect();
+            let new_keys: Vec<_> = new.iter().map(|e| (e.ip.as_str(), e.domains.first())).collect();
+            match_in_order(&old_keys, &new_keys)
+        }
+        2 => {
+            let old_keys: Vec<_> = old.iter().map(|e| e.domains.first()).collect();
+            let new_keys: Vec<_> = new.iter().map(|e| e.domains.first()).collect();
+            match_in_order(&old_keys, &new_keys)
+        }
+        _ => return Vec::new(),
+    };
+
+    let mut matches = Vec::with_capacity(found.len());
+    let mut start = (0, 0);
+    for anchor in found.iter().copied().map(Some).chain(std::iter::once(None)) {
+        let (old_end, new_end) = anchor.unwrap_or((old.len(), new.len()));
+        matches.extend(
+            match_entries_from(&old[start.0..old_end], &new[start.1..new_end], level + 1)
+                .into_iter()
+                .map(|(o, n)| (start.0 + o, start.1 + n)),
+        );
+
+        if let Some((o, n)) = anchor {
+            matches.push((o, n));
+            start = (o + 1, n + 1);
+        }
+    }
+
+    matches
+}
+
+/// Classify a single line of a hosts file
+fn classify_line(raw: &str) -> LineKind {
+    let trimmed = raw.trim();
+
+    if trimmed.is_empty() {
+        return LineKind::Blank;
+    }
+
+    if is_marker(trimmed, MANAGED_BEGIN) {
+        return LineKind::ManagedBegin;
+    }
+    if is_marker(trimmed, MANAGED_END) {
+        return LineKind::ManagedEnd;
+    }
+
+    if let Some(body) = trimmed.strip_prefix('#') {
+        // A commented-out entry in any of the usual styles: `# 127.0.0.1 a`,
+        // `#127.0.0.1 a`, `##  10.0.0.1 b`, `#\t::1\tc`, `# # 10.0.0.2 d`.
+        // Prose that merely starts with an address stays a comment.
+        if let Some(entry) = parse_entry(raw).filter(is_disabled_entry) {
+            return LineKind::Entry(Box::new(entry));
+        }
+
+        if let Some(section) = parse_section(raw) {
+            return LineKind::Section(Box::new(section));
+        }
+        return LineKind::Comment(body.trim().to_string());
+    }
+
+    match parse_entry(raw) {
+        Some(entry) => LineKind::Entry(Box::new(entry)),
+        None => LineKind::Unknown,
+    }
+}
+
+/// Compare a marker comment, ignoring case and spacing after the `#`
+fn is_marker(trimmed: &str, marker: &str) -> bool {
+    let text = |s: &str| s.trim_start_matches('#').trim().to_ascii_lowercase();
+    trimmed.starts_with('#') && text(trimmed) == text(marker)
+}
+
+/// Whether a commented-out line really is an entry: an address followed
+/// only by names that could be hostnames
+fn is_disabled_entry(entry: &EntryLine) -> bool {
+    looks_like_ip(&entry.ip) && entry.domains.iter().all(|d| looks_like_hostname(d))
+}
+
+/// Only recognize a comment as a section if it:
+/// 1. Opens or closes with special markers (===, ---), e.g. `=== Dev ===`
+/// 2. Ends with colon AND has significant length (to avoid "For example:")
+/// 3. Is marked explicitly (starts with "SECTION:" or similar)
+fn is_section_title(comment_text: &str) -> bool {
+    let banner = |marker: &str| comment_text.starts_with(marker) || comment_text.ends_with(marker);
+    banner("===")
+        || banner("---")
+        || (comment_text.ends_with(':')
+            && comment_text.len() < 30
+            && !comment_text.to_lowercase().contains("example"))
+        || comment_text.to_uppercase().starts_with("SECTION:")
+}
+
+/// Parse a section banner, splitting the title from its decoration.
+/// Pure rulers such as `# ==========` are not sections.
+fn parse_section(raw: &str) -> Option<SectionLine> {
+    let line = raw.trim_end();
+    let (body, enabled) = match line.strip_suffix(SECTION_DISABLED_MARKER) {
+        Some(rest) => (rest.trim_end(), false),
+        None => (line, true),
+    };
+
+    let text = body.trim_start().strip_prefix('#')?.trim();
+    if !is_section_title(text) {
+        return None;
+    }
+
+    let is_decoration = |c: char| c.is_whitespace() || "#=-*~".contains(c);
+    let mut start = body.len() - body.trim_start_matches(is_decoration).len();
+    if body[start..].get(..8).is_some_and(|p| p.eq_ignore_ascii_case("SECTION:")) {
+        let rest = &body[start + 8..];
+        start += 8 + rest.len() - rest.trim_start().len();
+    }
+
+    let title = body[start..].trim_end_matches(|c: char| is_decoration(c) || c == ':');
+    if title.is_empty() {
+        return None;
+    }
+    let end = start + title.len();
+
+    Some(SectionLine {
+        id: String::new(),
+        title: title.to_string(),
+        enabled,
+        prefix: body[..start].to_string(),
+        suffix: body[end..].to_string(),
+    })
+}
+
+/// Id derived from a section title or hostname, unique among `used`
+fn unique_id(text: &str, fallback: &str, used: &mut HashSet<String>) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    let base = if slug.is_empty() { fallback } else { slug };
+
+    let mut id = base.to_string();
+    let mut n = 2;
+    while !used.insert(id.clone()) {
+        id = format!("{}-{}", base, n);
+        n += 1;
+    }
+    id
+}
+
+fn looks_like_ip(token: &str) -> bool {
+    let address = token.split('%').next().unwrap_or(token);
+    address.parse::<IpAddr>().is_ok()
+}
+
+/// Loose hostname shape: the label characters the validator allows, plus
+/// letters of internationalized names. Stricter checks are left to the
+/// linter.
+fn looks_like_hostname(token: &str) -> bool {
+    let allowed = |c: char| validation::is_label_char(c) || (!c.is_ascii() && c.is_alphanumeric());
+    token.chars().next().is_some_and(|c| c != '-' && allowed(c)) && token.chars().all(|c| c == '.' || allowed(c))
+}
+
+/// Parse an entry line into tokens and layout. Commented-out lines start
+/// with one or more `#`, possibly mixed with whitespace (`#`, `## `,
+/// `#\t`, `# # `); the prefix is kept as written.
+fn parse_entry(raw: &str) -> Option<EntryLine> {
+    let indent_len = raw.len() - raw.trim_start().len();
+    let mut layout = EntryLayout {
+        indent: raw[..indent_len].to_string(),
+        ..EntryLayout::default()
+    };
+
+    let mut body = &raw[indent_len..];
+    let mut hashes = 0;
+    if body.starts_with('#') {
+        let rest = body.trim_start_matches(|c: char| c == '#' || c.is_whitespace());
+        let prefix = &body[..body.len() - rest.len()];
+        hashes = prefix.matches('#').count();
+        layout.disabled_prefix = Some(prefix.to_string());
+        body = rest;
+    }
+
+    let (entry_part, comment_part) = match body.find('#') {
+        Some(pos) => (&body[..pos], Some(&body[pos..])),
+        None => (body, None),
+    };
+
+    // Walk the tokens, remembering the whitespace before and column of each
+    let mut tokens: Vec<(String, String, usize)> = Vec::new();
+    let mut col = 0;
+    let mut rest = entry_part;
+    let tail_gap = loop {
+        let ws_len = rest.len() - rest.trim_start().len();
+        let ws = &rest[..ws_len];
+        col += ws.chars().count();
+        rest = &rest[ws_len..];
+        if rest.is_empty() {
+            break ws.to_string();
+        }
+        let token_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let token = &rest[..token_len];
+        tokens.push((token.to_string(), ws.to_string(), col));
+        col += token.chars().count();
+        rest = &rest[token_len..];
+    };
+
+    if tokens.len() < 2 {
+        return None;
+    }
+
+    layout.gaps = tokens[1..].iter().map(|(_, gap, _)| gap.clone()).collect();
+    layout.domain_cols = tokens[1..].iter().map(|(_, _, c)| *c).collect();
+
+    let comment = match comment_part {
+        Some(part) => {
+            let after = &part[1..];
+            let marker_len = after.len() - after.trim_start().len();
+            let text = after.trim();
+            layout.comment_gap = Some(tail_gap);
+            layout.comment_col = Some(col);
+            layout.comment_marker = Some(part[..marker_len + 1].to_string());
+            layout.trailing = after[marker_len + text.len()..].to_string();
+            Some(text.to_string())
+        }
+        None => {
+            layout.trailing = tail_gap;
+            None
+        }
+    };
+
+    let mut tokens = tokens.into_iter().map(|(token, _, _)| token);
+    let ip = tokens.next()?;
+
+    Some(EntryLine {
+        enabled: hashes == 0,
+        ip,
+        domains: tokens.collect(),
+        comment,
+        section_off: false,
+        layout,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &[(&str, &str)] = &[
+        ("windows-default", include_str!("../tests/fixtures/windows-default.hosts")),
+        ("macos-default", include_str!("../tests/fixtures/macos-default.hosts")),
+        ("debian", include_str!("../tests/fixtures/debian.hosts")),
+        ("blocklist", include_str!("../tests/fixtures/blocklist.hosts")),
+        ("dev-mixed", include_str!("../tests/fixtures/dev-mixed.hosts")),
+    ];
+
+    fn fixture(name: &str) -> &'static str {
+        FIXTURES.iter().find(|(n, _)| *n == name).map(|(_, content)| *content).unwrap()
+    }
+
+    /// `(enabled, ip, domains)` of every entry
+    fn entries(content: &str) -> Vec<(bool, String, String)> {
+        HostsDocument::parse(content)
+            .to_hosts_data()
+            .entries
+            .into_iter()
+            .map(|e| (e.enabled, e.ip, e.domains.join(" ")))
+            .collect()
+    }
+
+    fn entry(enabled: bool, ip: &str, domains: &str) -> (bool, String, String) {
+        (enabled, ip.to_string(), domains.to_string())
+    }
+
+    fn line_kinds(content: &str) -> Vec<LineKind> {
+        HostsDocument::parse(content).lines().iter().map(|l| l.kind.clone()).collect()
+    }
+
+    #[test]
+    fn fixtures_round_trip_unchanged() {
+        for (name, content) in FIXTURES {
+            let mut document = HostsDocument::parse(content);
+            assert_eq!(document.to_string(), *content, "{} did not render back as read", name);
+
+            let data = document.to_hosts_data();
+            document.apply(&data);
+            assert_eq!(document.to_string(), *content, "{} changed by applying its own data", name);
+        }
+    }
+
+    #[test]
+    fn windows_default_examples_are_disabled_entries() {
+        assert_eq!(
+            entries(fixture("windows-default")),
+            vec![
+                entry(false, "102.54.94.97", "rhino.acme.com"),
+                entry(false, "38.25.63.10", "x.acme.com"),
+                entry(false, "127.0.0.1", "localhost"),
+                entry(false, "::1", "localhost"),
+            ]
+        );
+        assert!(HostsDocument::parse(fixture("windows-default")).to_hosts_data().sections.is_empty());
+    }
+
+    #[test]
+    fn system_defaults_are_active_entries() {
+        assert_eq!(
+            entries(fixture("macos-default")),
+            vec![
+                entry(true, "127.0.0.1", "localhost"),
+                entry(true, "255.255.255.255", "broadcasthost"),
+                entry(true, "::1", "localhost"),
+            ]
+        );
+        assert_eq!(
+            entries(fixture("debian")),
+            vec![
+                entry(true, "127.0.0.1", "localhost"),
+                entry(true, "127.0.1.1", "debian-box.example.org debian-box"),
+                entry(true, "::1", "localhost ip6-localhost ip6-loopback"),
+                entry(true, "ff02::1", "ip6-allnodes"),
+                entry(true, "ff02::2", "ip6-allrouters"),
+            ]
+        );
+    }
+
+    #[test]
+    fn blocklist_disabled_lines_without_space() {
+        let data = HostsDocument::parse(fixture("blocklist")).to_hosts_data();
+        assert!(data.sections.is_empty());
+
+        let disabled: Vec<_> = data.entries.iter().filter(|e| !e.enabled).collect();
+        assert_eq!(disabled.len(), 2);
+        assert_eq!(disabled[0].domains, ["wizhumpgyros.com"]);
+        assert_eq!(disabled[1].domains, ["ad.example.com"]);
+        assert_eq!(disabled[1].comment.as_deref(), Some("[whitelisted]"));
+    }
+
+    #[test]
+    fn commented_entries_in_every_style() {
+        let data = HostsDocument::parse(fixture("dev-mixed")).to_hosts_data();
+        let got: Vec<_> = data
+            .entries
+            .iter()
+            .map(|e| (e.enabled, e.ip.clone(), e.domains.join(" ")))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                entry(true, "127.0.0.1", "app.local api.local"),
+                entry(false, "127.0.0.1", "staging.local"),
+                entry(false, "10.0.0.1", "old-db.internal"),
+                entry(false, "192.168.1.20", "printer.lan"),
+                entry(false, "10.0.0.2", "nested.internal"),
+                entry(false, "10.0.0.3", "indented.internal"),
+                entry(false, "fe80::1%lo0", "link-local.test"),
+                entry(true, "10.1.0.5", "staging.example.com"),
+                entry(false, "10.1.0.6", "staging-2.example.com"),
+            ]
+        );
+
+        let titles: Vec<_> = data.sections.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Development", "Staging"]);
+    }
+
+    #[test]
+    fn prose_and_rulers_stay_comments() {
+        let kinds = line_kinds(
+            "# 10.0.0.1 is the old gateway, do not use it\n\
+             # 10.0.0.9\n\
+             # TODO: move these --- ask ops first\n\
+             #=====================================\n\
+             # 127.0.0.1 - 127.255.255.255 loopback\n",
+        );
+        assert!(kinds.iter().all(|k| matches!(k, LineKind::Comment(_))), "{:?}", kinds);
+    }
+
+    #[test]
+    fn banners_are_sections() {
+        let kinds = line_kinds("# === Dev ===\n# --- Staging\n# Production ---\n# SECTION: Lab\n");
+        assert!(kinds.iter().all(|k| matches!(k, LineKind::Section(_))), "{:?}", kinds);
+    }
+
+    #[test]
+    fn re_enabling_restores_the_original_line() {
+        let styles = [
+            "#127.0.0.1  staging.local",
+            "##  10.0.0.1 old-db.internal",
+            "#\t192.168.1.20\tprinter.lan\t# office printer",
+            "# # 10.0.0.2 nested.internal",
+            "  # 10.0.0.3 indented.internal",
+        ];
+
+        for original in styles {
+            let content = format!("{}\n", original);
+            let mut document = HostsDocument::parse(&content);
+            let mut data = document.to_hosts_data();
+
+            data.entries[0].enabled = true;
+            document.apply(&data);
+            let enabled = document.to_string();
+            assert!(!enabled.trim_start().starts_with('#'), "{:?} stayed disabled", original);
+
+            data.entries[0].enabled = false;
+            document.apply(&data);
+            assert_eq!(document.to_string(), content);
+        }
+    }
+
+    /// Parse `content`, let `edit` change its data and render the result
+    fn edited(content: &str, edit: impl FnOnce(&mut HostsData)) -> String {
+        let mut document = HostsDocument::parse(content);
+        let mut data = document.to_hosts_data();
+        edit(&mut data);
+        document.apply(&data);
+        document.to_string()
+    }
+
+    #[test]
+    fn moved_entry_keeps_its_text() {
+        let content = "# office\n\
+                       \n\
+                       10.0.0.1 a.local   # first\n\
+                       10.0.0.2\tb.local\n\
+                       10.0.0.3  c.local\n";
+        let got = edited(content, |data| {
+            let moved = data.entries.remove(2);
+            data.entries.insert(0, moved);
+        });
+        assert_eq!(
+            got,
+            "# office\n\
+             \n\
+             10.0.0.3  c.local\n\
+             10.0.0.1 a.local   # first\n\
+             10.0.0.2\tb.local\n"
+        );
+    }
+
+    #[test]
+    fn inserted_entry_leaves_its_neighbours_alone() {
+        let content = "10.0.0.1\ta.local\t# first\n\
+                       \n\
+                       10.0.0.2   b.local\n";
+        // The new line copies the separator of the entry above it
+        let got = edited(content, |data| {
+            let mut added = data.entries[0].clone();
+            added.id = String::new();
+            added.ip = "10.0.0.9".to_string();
+            added.domains = vec!["new.local".to_string()];
+            added.comment = None;
+            data.entries.insert(1, added);
+        });
+        assert_eq!(
+            got,
+            "10.0.0.1\ta.local\t# first\n\
+             10.0.0.9\tnew.local\n\
+             \n\
+             10.0.0.2   b.local\n"
+        );
+    }
+
+    #[test]
+    fn deleted_entry_leaves_nearby_comments() {
+        let content = "10.0.0.1 a.local\n\
+                       # b.local moved to the new gateway\n\
+                       \n\
+                       10.0.0.2 b.local\n\
+                       # end of office hosts\n";
+        let got = edited(content, |data| data.entries.retain(|e| e.domains[0] != "b.local"));
+        assert_eq!(
+            got,
+            "10.0.0.1 a.local\n\
+             # b.local moved to the new gateway\n\
+             \n\
+             # end of office hosts\n"
+        );
+    }
+
+    #[test]
+    fn toggling_in_a_crlf_file_keeps_crlf() {
+        let content = "127.0.0.1 localhost\r\n\
+                       10.0.0.1\ta.local # office\r\n\
+                       10.0.0.2 b.local\r\n";
+        let disabled = edited(content, |data| data.entries[1].enabled = false);
+        assert_eq!(
+            disabled,
+            "127.0.0.1 localhost\r\n\
+             # 10.0.0.1\ta.local # office\r\n\
+             10.0.0.2 b.local\r\n"
+        );
+        assert_eq!(edited(&disabled, |data| data.entries[1].enabled = true), content);
+    }
+
+    #[test]
+    fn sorting_keeps_sections_and_comments_in_place() {
+        let mut document = HostsDocument::parse(
+            "# === Dev ===\n\
+             10.0.0.3 c.local\n\
+             # mirror of b\n\
+             10.0.0.2 b.local\n\
+             \n\
+             # notes about the rest\n\
+             \n\
+             10.0.0.1 a.local\n\
+             # === Other ===\n\
+             10.0.0.9 z.local\n\
+             10.0.0.8 y.local",
+        );
+        document.sort_entries();
+        assert_eq!(
+            document.to_string(),
+            "# === Dev ===\n\
+             10.0.0.1 a.local\n\
+             # mirror of b\n\
+             10.0.0.2 b.local\n\
+             \n\
+             # notes about the rest\n\
+             \n\
+             10.0.0.3 c.local\n\
+             # === Other ===\n\
+             10.0.0.8 y.local\n\
+             10.0.0.9 z.local"
+        );
+    }
+
+    #[test]
+    fn carried_ids_follow_entries_across_versions() {
+        let before = HostsDocument::parse("10.0.0.1 a.local\n10.0.0.3 b.local\n").to_hosts_data().entries;
+        // Another program puts a same-named entry above and renames b.local
+        let mut after = HostsDocument::parse("10.0.0.9 a.local\n10.0.0.1 a.local\n10.0.0.3 c.local\n")
+            .to_hosts_data()
+            .entries;
+        assert_eq!(after[1].id, "a-local-2");
+
+        carry_entry_ids(&before, &mut after);
+        let ids: Vec<&str> = after.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["a-local-2", "a-local", "b-local"]);
+    }
+}
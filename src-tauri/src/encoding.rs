@@ -0,0 +1,270 @@
+//! Decoding and encoding of the hosts file bytes.
+//!
+//! Hosts files touched by other Windows tools are not always UTF-8: UTF-16
+//! (with or without a byte order mark) and legacy single-byte files are
+//! common. The file is decoded into text for the document model and written
+//! back in the encoding it was read in.
+
+use crate::document::LineEnding;
+use crate::error::{AppError, ErrorCode};
+use crate::models::{FileFormat, LineStyle, TextEncoding};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Text of a hosts file together with how it was stored
+#[derive(Debug, Clone)]
+pub struct Decoded {
+    pub text: String,
+    pub encoding: TextEncoding,
+    pub bom: bool,
+    /// 1-based lines that held undecodable data (replaced with U+FFFD) or
+    /// whose characters had to be guessed
+    pub lossy_lines: Vec<usize>,
+}
+
+impl Decoded {
+    /// Storage format, with the line ending style of the decoded text
+    pub fn format(&self, newline: LineEnding) -> FileFormat {
+        FileFormat {
+            encoding: self.encoding,
+            bom: self.bom,
+            line_ending: line_style(newline),
+        }
+    }
+}
+
+pub fn line_style(newline: LineEnding) -> LineStyle {
+    match newline {
+        LineEnding::CrLf => LineStyle::Crlf,
+        _ => LineStyle::Lf,
+    }
+}
+
+pub fn line_ending(style: LineStyle) -> LineEnding {
+    match style {
+        LineStyle::Lf => LineEnding::Lf,
+        LineStyle::Crlf => LineEnding::CrLf,
+    }
+}
+
+/// Detect the encoding of `bytes` and decode them.
+/// A byte order mark wins; otherwise UTF-16 is recognized by its zero
+/// bytes, then strict UTF-8 is tried, and anything else is read as Latin-1.
+pub fn decode(bytes: &[u8]) -> Decoded {
+    if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        return decode_utf8(rest, true);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+        return decode_utf16(rest, TextEncoding::Utf16Le, true);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16BE_BOM) {
+        return decode_utf16(rest, TextEncoding::Utf16Be, true);
+    }
+
+    if let Some(encoding) = sniff_utf16(bytes) {
+        return decode_utf16(bytes, encoding, false);
+    }
+    decode_utf8(bytes, false)
+}
+
+/// Encode text for writing in the given format. Line endings are already
+/// part of the text.
+pub fn encode(text: &str, format: &FileFormat) -> Result<Vec<u8>, AppError> {
+    let mut out = Vec::with_capacity(text.len() + 3);
+
+    match format.encoding {
+        TextEncoding::Utf8 => {
+            if format.bom {
+                out.extend_from_slice(UTF8_BOM);
+            }
+            out.extend_from_slice(text.as_bytes());
+        }
+        TextEncoding::Utf16Le => {
+            if format.bom {
+                out.extend_from_slice(UTF16LE_BOM);
+            }
+            for unit in text.encode_utf16() {
+                out.extend_from_slice(&unit.to_le_bytes());
+            }
+        }
+        TextEncoding::Utf16Be => {
+            if format.bom {
+                out.extend_from_slice(UTF16BE_BOM);
+            }
+            for unit in text.encode_utf16() {
+                out.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+        TextEncoding::Latin1 => {
+            // A UTF-8 mark in front of bytes that are not UTF-8 is kept as read
+            if format.bom {
+                out.extend_from_slice(UTF8_BOM);
+            }
+            for (idx, line) in text.split('\n').enumerate() {
+                if idx > 0 {
+                    out.push(b'\n');
+                }
+                for c in line.chars() {
+                    let byte = u8::try_from(u32::from(c)).map_err(|_| {
+                        let message = format!(
+                            "Line {} contains '{}', which cannot be written in Latin-1; convert the file to UTF-8 first",
+                            idx + 1,
+                            c
+                        );
+                        AppError::new(ErrorCode::Unencodable, message).with_line(idx + 1)
+                    })?;
+                    out.push(byte);
+                }
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Strict UTF-8, falling back to Latin-1 (which never fails) when the
+/// bytes are not valid UTF-8
+fn decode_utf8(bytes: &[u8], bom: bool) -> Decoded {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Decoded {
+            text: text.to_string(),
+            encoding: TextEncoding::Utf8,
+            bom,
+            lossy_lines: Vec::new(),
+        };
+    }
+
+    // Every byte maps to a character, so the file round-trips exactly;
+    // only lines with non-ASCII bytes may show the wrong characters
+    let mut lossy_lines = Vec::new();
+    for (idx, line) in bytes.split(|&b| b == b'\n').enumerate() {
+        if !line.is_ascii() {
+            lossy_lines.push(idx + 1);
+        }
+    }
+
+    Decoded {
+        text: bytes.iter().map(|&b| char::from(b)).collect(),
+        encoding: TextEncoding::Latin1,
+        bom,
+        lossy_lines,
+    }
+}
+
+fn decode_utf16(bytes: &[u8], encoding: TextEncoding, bom: bool) -> Decoded {
+    let units = bytes.chunks(2).map(|pair| match (encoding, pair) {
+        (TextEncoding::Utf16Be, [hi, lo]) => u16::from_be_bytes([*hi, *lo]),
+        (_, [lo, hi]) => u16::from_le_bytes([*lo, *hi]),
+        // A dangling odd byte can't be a character
+        _ => 0xD800,
+    });
+
+    let mut text = String::with_capacity(bytes.len() / 2);
+    let mut lossy_lines = Vec::new();
+    let mut line = 1;
+    for c in char::decode_utf16(units) {
+        let c = c.unwrap_or_else(|_| {
+            if lossy_lines.last() != Some(&line) {
+                lossy_lines.push(line);
+            }
+            char::REPLACEMENT_CHARACTER
+        });
+        if c == '\n' {
+            line += 1;
+        }
+        text.push(c);
+    }
+
+    Decoded {
+        text,
+        encoding,
+        bom,
+        lossy_lines,
+    }
+}
+
+/// Recognize UTF-16 without a byte order mark: mostly-ASCII text has a
+/// zero in every other byte
+fn sniff_utf16(bytes: &[u8]) -> Option<TextEncoding> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let sample = &bytes[..bytes.len().min(4096)];
+    let pairs = sample.len() / 2;
+    let zeros_at = |offset: usize| sample.iter().skip(offset).step_by(2).filter(|&&b| b == 0).count();
+
+    if zeros_at(1) * 2 > pairs && zeros_at(0) == 0 {
+        Some(TextEncoding::Utf16Le)
+    } else if zeros_at(0) * 2 > pairs && zeros_at(1) == 0 {
+        Some(TextEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "127.0.0.1 localhost\r\n# caf\u{e9} \u{1F642}\r\n::1 localhost\n";
+
+    /// Decode `bytes`, check what was detected and encode them back
+    fn round_trip(bytes: &[u8], encoding: TextEncoding, bom: bool) -> Decoded {
+        let decoded = decode(bytes);
+        assert_eq!((decoded.encoding, decoded.bom), (encoding, bom));
+        let encoded = encode(&decoded.text, &decoded.format(LineEnding::Lf)).unwrap();
+        assert_eq!(encoded, bytes);
+        decoded
+    }
+
+    fn with_bom(bom: &[u8], content: &[u8]) -> Vec<u8> {
+        [bom, content].concat()
+    }
+
+    #[test]
+    fn utf8_round_trips() {
+        for (bytes, bom) in [(TEXT.as_bytes().to_vec(), false), (with_bom(UTF8_BOM, TEXT.as_bytes()), true)] {
+            let decoded = round_trip(&bytes, TextEncoding::Utf8, bom);
+            assert_eq!(decoded.text, TEXT);
+            assert!(decoded.lossy_lines.is_empty());
+        }
+    }
+
+    #[test]
+    fn utf16_round_trips() {
+        let le: Vec<u8> = TEXT.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let be: Vec<u8> = TEXT.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        let cases = [
+            (le.clone(), TextEncoding::Utf16Le, false),
+            (with_bom(UTF16LE_BOM, &le), TextEncoding::Utf16Le, true),
+            (be.clone(), TextEncoding::Utf16Be, false),
+            (with_bom(UTF16BE_BOM, &be), TextEncoding::Utf16Be, true),
+        ];
+        for (bytes, encoding, bom) in cases {
+            let decoded = round_trip(&bytes, encoding, bom);
+            assert_eq!(decoded.text, TEXT);
+            assert!(decoded.lossy_lines.is_empty());
+        }
+    }
+
+    #[test]
+    fn latin1_round_trips() {
+        let content = b"127.0.0.1 localhost\r\n10.0.0.1 caf\xe9.local # \xa9 office\r\n";
+        for (bytes, bom) in [(content.to_vec(), false), (with_bom(UTF8_BOM, content), true)] {
+            let decoded = round_trip(&bytes, TextEncoding::Latin1, bom);
+            assert_eq!(decoded.text, "127.0.0.1 localhost\r\n10.0.0.1 caf\u{e9}.local # \u{a9} office\r\n");
+            assert_eq!(decoded.lossy_lines, [2]);
+        }
+    }
+
+    #[test]
+    fn latin1_refuses_other_characters() {
+        let format = FileFormat { encoding: TextEncoding::Latin1, bom: false, line_ending: LineStyle::Lf };
+        let error = encode("127.0.0.1 localhost\n10.0.0.1 a.local # \u{20ac}\n", &format).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Unencodable);
+        assert_eq!(error.line, Some(2));
+    }
+}
@@ -0,0 +1,192 @@
+//! Errors returned to the UI.
+//!
+//! Every error has a stable `code` the frontend can translate and act on,
+//! and an English `message` to fall back to. Where they apply, it also
+//! names the file, the OS error and the line involved, and the error that
+//! caused it as `source`. Refused saves carry what the UI needs to resolve
+//! them as `details`.
+
+use crate::protect::ProtectedChange;
+use crate::revision::Conflict;
+use crate::validation::EntryError;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCode {
+    /// A file or directory does not exist
+    NotFound,
+    /// The OS refused access to a file
+    PermissionDenied,
+    ReadFailed,
+    WriteFailed,
+    BackupFailed,
+    BackupNotFound,
+    /// A backup whose stored data is missing or fails its checksum
+    CorruptedBackup,
+    /// The file or DNS did not show the saved content afterwards
+    VerifyFailed,
+    /// Another EasyHosts process is changing the hosts file
+    Locked,
+    /// A hosts path or target that can't be used
+    InvalidPath,
+    UnknownTarget,
+    /// Entries, patterns or imported data that don't check out
+    InvalidData,
+    UnsupportedVersion,
+    /// Text that can't be written in the file's encoding
+    Unencodable,
+    EntryNotFound,
+    SectionNotFound,
+    AlreadyManaged,
+    /// The file changed on disk since it was read
+    Conflict,
+    /// A change would drop or redirect protected system entries
+    ProtectedEntries,
+    /// The version of the file that was edited is no longer known
+    RevisionUnavailable,
+    DnsLookupFailed,
+    DnsFlushFailed,
+    PingTimeout,
+    /// An external program could not be run or failed
+    CommandFailed,
+    /// Not available on this platform
+    Unsupported,
+    /// A setting held by the backend could not be read or changed
+    SettingsUnavailable,
+    Other,
+}
+
+/// An OS error, as reported by the standard library
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OsError {
+    /// `io::ErrorKind` in kebab case, e.g. `permission-denied`
+    pub kind: String,
+    /// Raw `errno` or Windows error code
+    pub code: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub path: Option<String>,
+    pub os_error: Option<OsError>,
+    /// 1-based line the error refers to
+    pub line: Option<usize>,
+    /// The error that caused this one
+    pub source: Option<Box<AppError>>,
+    /// What a refused save ran into, for codes that have any
+    #[serde(skip_deserializing)]
+    pub details: Option<Box<ErrorDetails>>,
+}
+
+/// Data that comes with a refused save
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ErrorDetails {
+    /// With `ErrorCode::Conflict`
+    Conflict(Box<Conflict>),
+    /// With `ErrorCode::ProtectedEntries`
+    Protected { changes: Vec<ProtectedChange> },
+    /// With `ErrorCode::InvalidData`: the added or edited entries that
+    /// don't check out
+    Invalid { errors: Vec<EntryError> },
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError {
+            code,
+            message: message.into(),
+            path: None,
+            os_error: None,
+            line: None,
+            source: None,
+            details: None,
+        }
+    }
+
+    /// Failure to `action` the file at `path`, e.g. "read hosts file".
+    /// Missing files and denied access get their own codes.
+    pub fn io(code: ErrorCode, action: &str, path: &Path, error: &io::Error) -> Self {
+        let code = match error.kind() {
+            io::ErrorKind::NotFound => ErrorCode::NotFound,
+            io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            _ => code,
+        };
+        AppError::new(code, format!("Failed to {} {}: {}", action, path.display(), error))
+            .with_path(path)
+            .with_os_error(error)
+    }
+
+    /// Failure to run `program`
+    pub fn command(program: &str, error: &io::Error) -> Self {
+        AppError::new(ErrorCode::CommandFailed, format!("Failed to run {}: {}", program, error))
+            .with_os_error(error)
+    }
+
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_string_lossy().to_string());
+        self
+    }
+
+    pub fn with_line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+
+    pub fn with_os_error(mut self, error: &io::Error) -> Self {
+        self.os_error = Some(OsError {
+            kind: kebab_case(&format!("{:?}", error.kind())),
+            code: error.raw_os_error(),
+        });
+        self
+    }
+
+    pub fn with_source(mut self, source: AppError) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    pub fn with_details(mut self, details: ErrorDetails) -> Self {
+        self.details = Some(Box::new(details));
+        self
+    }
+
+    /// A backend setting that could not be read or changed
+    pub fn settings(action: &str) -> Self {
+        AppError::new(ErrorCode::SettingsUnavailable, format!("Failed to {}", action))
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref().map(|e| e as &(dyn Error + 'static))
+    }
+}
+
+fn kebab_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('-');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
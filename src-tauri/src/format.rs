@@ -0,0 +1,176 @@
+//! Canonical formatting of hosts files ("hosts fmt").
+//!
+//! Formatting works on the document model, so comments, blank lines and
+//! sections stay where they are; only entry lines are rewritten.
+
+use crate::diff;
+use crate::document::HostsDocument;
+use crate::models::{HostEntry, HostsData};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How hostnames are distributed over lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineLayout {
+    /// Keep entries as they are
+    #[default]
+    Preserve,
+    /// One hostname per line
+    OnePerLine,
+    /// Merge entries with the same IP (and state) within a section
+    GroupByIp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatOptions {
+    /// Line up hostnames and comments in columns
+    pub align: bool,
+    pub layout: LineLayout,
+    pub lowercase: bool,
+    /// Sort entries by hostname within each section
+    pub sort: bool,
+    /// Drop entries repeating an earlier one in the same section
+    pub collapse_duplicates: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            align: true,
+            layout: LineLayout::Preserve,
+            lowercase: true,
+            sort: false,
+            collapse_duplicates: true,
+        }
+    }
+}
+
+/// Formatted text with a unified diff against the input
+#[derive(Debug, Clone, Serialize)]
+pub struct Formatted {
+    pub text: String,
+    pub changed: bool,
+    pub diff: String,
+}
+
+/// Format hosts file content without writing anything
+pub fn format_hosts(content: &str, options: &FormatOptions, max_aliases: usize) -> Formatted {
+    let mut document = HostsDocument::parse(content).with_max_aliases(max_aliases);
+    format_document(&mut document, options);
+
+    let text = document.to_string();
+    Formatted {
+        changed: text != content,
+        diff: diff::unified_diff(content, &text, "hosts", "hosts (formatted)"),
+        text,
+    }
+}
+
+/// Format a document in place
+pub fn format_document(document: &mut HostsDocument, options: &FormatOptions) {
+    let data = document.to_hosts_data();
+    let mut entries = data.entries.clone();
+
+    if options.lowercase {
+        for entry in &mut entries {
+            for domain in &mut entry.domains {
+                *domain = domain.to_lowercase();
+            }
+        }
+    }
+
+    entries = match options.layout {
+        LineLayout::Preserve => entries,
+        LineLayout::OnePerLine => one_per_line(entries),
+        LineLayout::GroupByIp => group_by_ip(entries),
+    };
+
+    if options.collapse_duplicates {
+        entries = collapse_duplicates(entries);
+    }
+
+    if entries != data.entries {
+        document.apply(&HostsData { entries, ..data });
+    }
+    if options.sort {
+        document.sort_entries();
+    }
+    if options.align {
+        document.align_columns();
+    }
+}
+
+/// Split every entry into one line per hostname. The comment stays with
+/// the first hostname; tags and metadata are copied to all of them.
+fn one_per_line(entries: Vec<HostEntry>) -> Vec<HostEntry> {
+    let mut out = Vec::with_capacity(entries.len());
+    for entry in entries {
+        for (i, domain) in entry.domains.iter().enumerate() {
+            out.push(HostEntry {
+                domains: vec![domain.clone()],
+                comment: entry.comment.clone().filter(|_| i == 0),
+                ..entry.clone()
+            });
+        }
+    }
+    out
+}
+
+/// Merge entries with the same section, state and IP into the first of them
+fn group_by_ip(entries: Vec<HostEntry>) -> Vec<HostEntry> {
+    let mut out: Vec<HostEntry> = Vec::new();
+    let mut first: HashMap<(Option<String>, bool, String), usize> = HashMap::new();
+
+    for entry in entries {
+        let key = (entry.section.clone(), entry.enabled, entry.ip.clone());
+        let Some(&idx) = first.get(&key) else {
+            first.insert(key, out.len());
+            out.push(entry);
+            continue;
+        };
+
+        let target = &mut out[idx];
+        for domain in entry.domains {
+            if !target.domains.iter().any(|d| d.eq_ignore_ascii_case(&domain)) {
+                target.domains.push(domain);
+            }
+        }
+        for tag in entry.tags {
+            if !target.tags.contains(&tag) {
+                target.tags.push(tag);
+            }
+        }
+        if target.comment.is_none() {
+            target.comment = entry.comment;
+        }
+    }
+
+    out
+}
+
+/// Drop entries that repeat an earlier one in the same section, keeping a
+/// comment from the dropped copy if the first had none
+fn collapse_duplicates(entries: Vec<HostEntry>) -> Vec<HostEntry> {
+    let mut out: Vec<HostEntry> = Vec::new();
+    let mut first: HashMap<(Option<String>, bool, String, Vec<String>), usize> = HashMap::new();
+
+    for entry in entries {
+        let domains = entry.domains.iter().map(|d| d.to_lowercase()).collect();
+        let key = (entry.section.clone(), entry.enabled, entry.ip.clone(), domains);
+        match first.get(&key) {
+            Some(&idx) => {
+                if out[idx].comment.is_none() {
+                    out[idx].comment = entry.comment;
+                }
+            }
+            None => {
+                first.insert(key, out.len());
+                out.push(entry);
+            }
+        }
+    }
+
+    out
+}
@@ -1,169 +1,416 @@
-use crate::models::{HostEntry, HostsData, Section};
+use crate::atomic;
+use crate::backup::{self, BackupInfo, BackupMeta, BackupReason};
+use crate::diff::{self, HostsDiff};
+use crate::document::{self, HostsDocument};
+use crate::encoding::{self, Decoded};
+use crate::error::{AppError, ErrorCode};
+use crate::format::{self, FormatOptions};
+use crate::lock::{self, HostsLock};
+use crate::merge::{self, MergeResult, Side};
+use crate::models::{FileFormat, HostEntry, HostsData};
+use crate::probe;
+use crate::protect;
+use crate::revision::{self, Conflict, Revision};
+use crate::target;
+use crate::validation;
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
-use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct BackupInfo {
-    pub filename: String,
-    pub path: String,
-    pub timestamp: String,
-    pub size: u64,
+/// Get the hosts file path in use: the system one unless configured
+/// otherwise (see `target`)
+pub fn get_hosts_path() -> PathBuf {
+    target::active().0
 }
 
-/// Get the OS-specific hosts file path
-pub fn get_hosts_path() -> PathBuf {
-    #[cfg(target_os = "windows")]
-    {
-        PathBuf::from(r"C:\Windows\System32\drivers\etc\hosts")
-    }
-    
-    #[cfg(not(target_os = "windows"))]
-    {
-        PathBuf::from("/etc/hosts")
+/// Hold off other EasyHosts processes while changing the hosts file.
+/// The lock file sits in the file's folder of the backup store, so nothing
+/// is added next to the hosts file.
+pub fn lock_hosts() -> Result<HostsLock, AppError> {
+    lock::acquire(&backup::dir_for(&get_hosts_path())?)
+}
+
+/// Read and decode the hosts file, whatever encoding it is stored in
+pub fn read_hosts_decoded() -> Result<Decoded, AppError> {
+    read_hosts_revision().map(|(decoded, _)| decoded)
+}
+
+/// Read and decode the hosts file, with the revision of the bytes read
+fn read_hosts_revision() -> Result<(Decoded, Revision), AppError> {
+    let (bytes, revision) = read_hosts_bytes()?;
+    Ok((encoding::decode(&bytes), revision))
+}
+
+fn read_hosts_bytes() -> Result<(Vec<u8>, Revision), AppError> {
+    let path = get_hosts_path();
+
+    let bytes = fs::read(&path)
+        .map_err(|e| AppError::io(ErrorCode::ReadFailed, "read hosts file", &path, &e))?;
+    let revision = Revision::new(&bytes, fs::metadata(&path).ok().as_ref());
+    Ok((bytes, revision))
+}
+
+/// Read the hosts file into structured data, including how it is stored,
+/// which lines could not be decoded exactly and the revision read
+pub fn read_hosts(max_aliases: usize) -> Result<HostsData, AppError> {
+    let (decoded, current) = read_hosts_revision()?;
+    let document = HostsDocument::parse(&decoded.text).with_max_aliases(max_aliases);
+    revision::remember(&current, &decoded.text);
+
+    let mut data = hosts_data(&document);
+    data.format = Some(decoded.format(document.newline()));
+    data.lossy_lines = decoded.lossy_lines;
+    data.revision = Some(current);
+    Ok(data)
+}
+
+fn conflict(data: &HostsData, decoded: &Decoded, current: Revision, max_aliases: usize) -> Option<Conflict> {
+    let expected = data.revision.as_ref().filter(|r| !r.matches(&current))?;
+
+    // What changed can only be told if the content read is still known
+    let changes = revision::recall(expected)
+        .map(|base| diff_contents(&base, &decoded.text, "read", "on disk", max_aliases));
+
+    Some(Conflict {
+        expected: expected.clone(),
+        current,
+        changes,
+    })
+}
+
+/// Entry by entry and text diff between two versions of the file
+fn diff_contents(old: &str, new: &str, old_label: &str, new_label: &str, max_aliases: usize) -> HostsDiff {
+    let all_entries = |data: HostsData| {
+        let mut entries = data.entries;
+        entries.extend(data.foreign.map(|f| f.entries).unwrap_or_default());
+        entries
+    };
+
+    let before = all_entries(parse_hosts(old, max_aliases));
+    let mut after = all_entries(parse_hosts(new, max_aliases));
+    document::carry_entry_ids(&before, &mut after);
+
+    HostsDiff {
+        entries: diff::entry_changes(&before, &after),
+        text: diff::unified_diff(old, new, old_label, new_label),
     }
 }
 
-/// Read the hosts file and return raw content
-pub fn read_hosts_file() -> Result<String, String> {
+/// Show what saving `data` would change, without writing anything
+pub fn preview_save(data: &HostsData, max_aliases: usize) -> Result<HostsDiff, AppError> {
+    let decoded = read_hosts_decoded()?;
+    let mut document = HostsDocument::parse(&decoded.text).with_max_aliases(max_aliases);
+    document.apply(&validation::encode_idn(data));
+
+    Ok(diff_contents(&decoded.text, &document.to_string(), "hosts", "hosts (after save)", max_aliases))
+}
+
+/// Show what restoring a backup would change
+pub fn preview_restore(id: &str, max_aliases: usize) -> Result<HostsDiff, AppError> {
+    let decoded = read_hosts_decoded()?;
+    let backup = backup::read(&get_hosts_path(), id)?;
+
+    Ok(diff_contents(&decoded.text, &encoding::decode(&backup).text, "hosts", id, max_aliases))
+}
+
+/// Parse hosts file content into structured data.
+/// Punycode hostnames are shown in their Unicode form, and lines split at
+/// `max_aliases` names are folded back into one entry.
+pub fn parse_hosts(content: &str, max_aliases: usize) -> HostsData {
+    let content = content.strip_prefix('\u{FEFF}').unwrap_or(content);
+    hosts_data(&HostsDocument::parse(content).with_max_aliases(max_aliases))
+}
+
+fn hosts_data(document: &HostsDocument) -> HostsData {
+    let mut data = document.to_hosts_data();
+    validation::decode_idn(&mut data);
+    data
+}
+
+/// Convert HostsData back to hosts file format,
+/// splitting entries with more than `max_aliases` names
+pub fn serialize_hosts(data: &HostsData, max_aliases: usize) -> String {
+    HostsDocument::from_hosts_data(data, max_aliases).to_string()
+}
+
+/// Back up the hosts file to the backup store (see `backup`).
+/// The caller holds the hosts lock.
+pub fn backup_hosts_file(reason: BackupReason, label: Option<&str>) -> Result<String, AppError> {
     let path = get_hosts_path();
-    
-    fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read hosts file at {:?}: {}", path, e))
-}
-
-/// Parse hosts file content into structured data
-pub fn parse_hosts(content: &str) -> HostsData {
-    let mut entries = Vec::new();
-    let mut sections = Vec::new();
-    let mut pending_comment: Option<String> = None;
-    
-    for line in content.lines() {
-        let trimmed = line.trim();
-        
-        // Skip empty lines
-        if trimmed.is_empty() {
-            continue;
-        }
-        
-        // Check if it's a comment-only line
-        if trimmed.starts_with('#') {
-            let comment_text = trimmed[1..].trim();
-
-            // Skip empty comments
-            if comment_text.is_empty() {
-                continue;
-            }
-
-            // Check if this looks like a section header
-            // Only recognize as section if:
-            // 1. Contains special markers (===, ---)
-            // 2. Ends with colon AND has significant length (to avoid "For example:")
-            // 3. Is marked explicitly (starts with "SECTION:" or similar)
-            let is_section = comment_text.contains("===")
-                || comment_text.contains("---")
-                || (comment_text.ends_with(':') && comment_text.len() < 30 && !comment_text.to_lowercase().contains("example"))
-                || comment_text.to_uppercase().starts_with("SECTION:");
-
-            if is_section {
-                sections.push(Section {
-                    title: comment_text.to_string(),
-                    enabled: true,
-                });
-                pending_comment = None;
-            } else {
-                // This is a standalone comment - save it for the next entry
-                pending_comment = Some(comment_text.to_string());
-            }
-            continue;
+    let content = fs::read(&path)
+        .map_err(|e| AppError::io(ErrorCode::BackupFailed, "read hosts file for backup", &path, &e))?;
+
+    backup::create(&path, &content, reason, label)
+}
+
+/// Save hosts data to file.
+/// The current file is applied the edits line by line, so comments,
+/// blank lines and formatting of untouched lines are preserved.
+/// Internationalized hostnames are written in punycode, and entries with
+/// more than `max_aliases` names are split over several lines.
+/// The file keeps its encoding, byte order mark and line endings.
+/// Fails with a conflict if the file changed since `data` was read, and
+/// refuses to drop or redirect protected system entries.
+pub fn save_hosts_file(data: &HostsData, max_aliases: usize) -> Result<Revision, AppError> {
+    let pending = prepare_save(data, false, max_aliases)?;
+    write_pending(&pending)
+}
+
+/// The hosts file as read, and as it will be written
+pub struct PendingSave {
+    pub original: Vec<u8>,
+    pub content: Vec<u8>,
+    text: String,
+}
+
+/// Work out what saving `data` writes, without writing it.
+/// Fails with a conflict if the file changed since `data` was read, if an
+/// added or edited entry is invalid, or if it would drop or redirect
+/// protected entries and `allow_protected` is off.
+pub fn prepare_save(data: &HostsData, allow_protected: bool, max_aliases: usize) -> Result<PendingSave, AppError> {
+    let (original, current) = read_hosts_bytes()?;
+    let decoded = encoding::decode(&original);
+    if let Some(conflict) = conflict(data, &decoded, current, max_aliases) {
+        return Err(revision::conflict_error(conflict));
+    }
+
+    let mut document = HostsDocument::parse(&decoded.text).with_max_aliases(max_aliases);
+    let before = hosts_data(&document);
+    validation::validate_changed(data, &before.entries).map_err(validation::invalid_entries)?;
+    document.apply(&validation::encode_idn(data));
+
+    if !allow_protected {
+        let changes = protect::check(&before, &hosts_data(&document));
+        if !changes.is_empty() {
+            return Err(protect::refused(changes));
         }
-        
-        // Parse entry line (enabled or disabled)
-        let (is_enabled, working_line) = if trimmed.starts_with("# ") {
-            (false, trimmed[2..].trim())
-        } else {
-            (true, trimmed)
-        };
-        
-        // Split by whitespace and #
-        let comment_split: Vec<&str> = working_line.splitn(2, '#').collect();
-        let entry_part = comment_split[0].trim();
-        let inline_comment = comment_split.get(1).map(|s| s.trim().to_string());
-        
-        // Skip if no entry part
-        if entry_part.is_empty() {
-            continue;
+    }
+
+    let text = document.to_string();
+    let content = encoding::encode(&text, &decoded.format(document.newline()))?;
+    Ok(PendingSave { original, content, text })
+}
+
+/// Write a prepared save, returning the revision written
+pub fn write_pending(pending: &PendingSave) -> Result<Revision, AppError> {
+    write_hosts_bytes(&pending.content, &pending.text)
+}
+
+/// Merge edits made to an earlier revision of the file with the file as it
+/// is now. Nothing is written; the merged data can be saved once the
+/// conflicts are resolved.
+pub fn merge_hosts(data: &HostsData, resolutions: &HashMap<String, Side>, max_aliases: usize) -> Result<MergeResult, AppError> {
+    let expected = data.revision.as_ref().ok_or_else(|| {
+        AppError::new(ErrorCode::RevisionUnavailable, "The edited data has no revision to merge from")
+    })?;
+    let base = revision::recall(expected).ok_or_else(|| {
+        AppError::new(
+            ErrorCode::RevisionUnavailable,
+            "The version that was edited is no longer available; reload the hosts file",
+        )
+    })?;
+
+    let base = parse_hosts(&base, max_aliases);
+    let mut theirs = read_hosts(max_aliases)?;
+    // Ids in `data` are those of the version that was edited
+    document::carry_entry_ids(&base.entries, &mut theirs.entries);
+    Ok(merge::merge(&base, data, &theirs, resolutions))
+}
+
+/// Encode a document in `format` and write it to the hosts file,
+/// returning the revision written
+fn write_hosts_document(document: &HostsDocument, format: &FileFormat) -> Result<Revision, AppError> {
+    let text = document.to_string();
+    let content = encoding::encode(&text, format)?;
+    write_hosts_bytes(&content, &text)
+}
+
+/// Write encoded `content` (decoding to `text`) to the hosts file
+fn write_hosts_bytes(content: &[u8], text: &str) -> Result<Revision, AppError> {
+    let path = get_hosts_path();
+    atomic::write_atomic(&path, content).map_err(|e| {
+        let error = AppError::io(ErrorCode::WriteFailed, "write hosts file", &path, &e);
+        // Name the cause when it can be found, e.g. an immutable file
+        match probe::probe_write(&path).message {
+            Some(message) => AppError::new(error.code, format!("Failed to write hosts file: {}", message))
+                .with_path(&path)
+                .with_source(error),
+            None => error,
         }
-        
-        // Parse IP and domains
-        let parts: Vec<&str> = entry_part.split_whitespace().collect();
-        if parts.len() >= 2 {
-            let ip = parts[0].to_string();
-            let domains: Vec<String> = parts[1..].iter().map(|s| s.to_string()).collect();
-            
-            // Determine final comment: prefer pending_comment, then inline_comment
-            let final_comment = pending_comment.take()
-                .or(inline_comment);
-            
-            entries.push(HostEntry {
-                enabled: is_enabled,
-                ip,
-                domains,
-                comment: final_comment,
-            });
+    })?;
+
+    let revision = Revision::new(content, fs::metadata(&path).ok().as_ref());
+    revision::remember(&revision, text);
+    Ok(revision)
+}
+
+/// Rewrite the hosts file in a different encoding or line ending style
+/// (with a backup), leaving its content unchanged
+pub fn convert_hosts_format(format: FileFormat, max_aliases: usize) -> Result<HostsData, AppError> {
+    let _lock = lock_hosts()?;
+    let decoded = read_hosts_decoded()?;
+    let mut document = HostsDocument::parse(&decoded.text);
+    document.convert_line_endings(encoding::line_ending(format.line_ending));
+
+    // Fail before the backup if the text does not fit the new encoding
+    encoding::encode(&document.to_string(), &format)?;
+
+    backup_hosts_file(BackupReason::Format, None)?;
+    write_hosts_document(&document, &format)?;
+
+    read_hosts(max_aliases)
+}
+
+/// Format the hosts file and write it back (with a backup)
+pub fn format_hosts_file(options: &FormatOptions, max_aliases: usize) -> Result<HostsData, AppError> {
+    let _lock = lock_hosts()?;
+    let decoded = read_hosts_decoded()?;
+    let mut document = HostsDocument::parse(&decoded.text).with_max_aliases(max_aliases);
+    format::format_document(&mut document, options);
+
+    backup_hosts_file(BackupReason::Format, None)?;
+    write_hosts_document(&document, &decoded.format(document.newline()))?;
+
+    read_hosts(max_aliases)
+}
+
+/// Apply a change to the current hosts file and write it back (with a backup),
+/// returning the data as it now reads from disk
+fn update_hosts_file<F>(max_aliases: usize, change: F) -> Result<HostsData, AppError>
+where
+    F: FnOnce(&mut HostsData) -> Result<(), AppError>,
+{
+    let _lock = lock_hosts()?;
+    let mut data = read_hosts(max_aliases)?;
+    change(&mut data)?;
+
+    backup_hosts_file(BackupReason::Edit, None)?;
+    save_hosts_file(&data, max_aliases)?;
+
+    read_hosts(max_aliases)
+}
+
+/// Apply a change to the entry with id `id`, at its index. `id` names an
+/// entry of the version read at `revision`; if the file changed since,
+/// that version is recalled and its ids are carried over to the file as it
+/// is now, so the id still finds the same entry.
+fn update_entry_by_id<F>(id: &str, revision: &Revision, max_aliases: usize, change: F) -> Result<HostsData, AppError>
+where
+    F: FnOnce(&mut HostsData, usize) -> Result<(), AppError>,
+{
+    update_hosts_file(max_aliases, |data| {
+        if !data.revision.as_ref().is_some_and(|current| current.matches(revision)) {
+            let base = revision::recall(revision).ok_or_else(|| {
+                AppError::new(
+                    ErrorCode::RevisionUnavailable,
+                    "The version the entry was picked from is no longer available; reload the hosts file",
+                )
+            })?;
+            document::carry_entry_ids(&parse_hosts(&base, max_aliases).entries, &mut data.entries);
         }
+        let idx = data.entries.iter()
+            .position(|e| e.id == id)
+            .ok_or_else(|| AppError::new(ErrorCode::EntryNotFound, format!("Entry not found: {}", id)))?;
+        change(data, idx)
+    })
+}
+
+/// Enable or disable a single entry
+pub fn set_entry_enabled(id: &str, enabled: bool, revision: &Revision, max_aliases: usize) -> Result<HostsData, AppError> {
+    update_entry_by_id(id, revision, max_aliases, |data, idx| {
+        data.entries[idx].enabled = enabled;
+        Ok(())
+    })
+}
+
+/// Replace an entry with an edited version, keeping its place in the file
+pub fn update_entry(id: &str, entry: HostEntry, revision: &Revision, max_aliases: usize) -> Result<HostsData, AppError> {
+    update_entry_by_id(id, revision, max_aliases, |data, idx| {
+        data.entries[idx] = entry;
+        Ok(())
+    })
+}
+
+/// Delete a single entry
+pub fn delete_entry(id: &str, revision: &Revision, max_aliases: usize) -> Result<HostsData, AppError> {
+    update_entry_by_id(id, revision, max_aliases, |data, idx| {
+        data.entries.remove(idx);
+        Ok(())
+    })
+}
+
+fn section_index(data: &HostsData, id: &str) -> Result<usize, AppError> {
+    data.sections.iter()
+        .position(|s| s.id == id)
+        .ok_or_else(|| AppError::new(ErrorCode::SectionNotFound, format!("Section not found: {}", id)))
+}
+
+/// Turn a section on or off; its entries are commented out as a unit
+pub fn set_section_enabled(id: &str, enabled: bool, max_aliases: usize) -> Result<HostsData, AppError> {
+    update_hosts_file(max_aliases, |data| {
+        let idx = section_index(data, id)?;
+        data.sections[idx].enabled = enabled;
+        Ok(())
+    })
+}
+
+/// Rename a section, keeping the decoration of its banner
+pub fn rename_section(id: &str, title: &str, max_aliases: usize) -> Result<HostsData, AppError> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(AppError::new(ErrorCode::InvalidData, "Section title cannot be empty"));
     }
-    
-    HostsData { entries, sections }
-}
-
-/// Convert HostsData back to hosts file format
-pub fn serialize_hosts(data: &HostsData) -> String {
-    let mut output = String::new();
-    
-    for entry in &data.entries {
-        let prefix = if entry.enabled { "" } else { "# " };
-        let domains = entry.domains.join(" ");
-        let comment_part = entry.comment.as_ref()
-            .map(|c| format!(" # {}", c))
-            .unwrap_or_default();
-        
-        output.push_str(&format!("{}{} {}{}\n", prefix, entry.ip, domains, comment_part));
-    }
-    
-    output
+
+    update_hosts_file(max_aliases, |data| {
+        let idx = section_index(data, id)?;
+        data.sections[idx].title = title.to_string();
+        Ok(())
+    })
 }
 
-/// Create a backup of the hosts file with timestamp
-pub fn backup_hosts_file() -> Result<String, String> {
-    let path = get_hosts_path();
-    let content = fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read hosts file for backup: {}", e))?;
-    
-    // Create backup filename with timestamp
-    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-    let backup_path = path.parent()
-        .ok_or("Failed to get parent directory")?
-        .join(format!("hosts.bak_{}", timestamp));
-    
-    fs::write(&backup_path, content)
-        .map_err(|e| format!("Failed to write backup file: {}", e))?;
-    
-    Ok(backup_path.to_string_lossy().to_string())
-}
-
-/// Save hosts data to file
-pub fn save_hosts_file(data: &HostsData) -> Result<(), String> {
-    let path = get_hosts_path();
-    let content = serialize_hosts(data);
+/// Move a section (with all of its lines) to a new position
+pub fn move_section(id: &str, index: usize, max_aliases: usize) -> Result<HostsData, AppError> {
+    update_hosts_file(max_aliases, |data| {
+        let idx = section_index(data, id)?;
+        let section = data.sections.remove(idx);
+        let index = index.min(data.sections.len());
+        data.sections.insert(index, section);
+        Ok(())
+    })
+}
+
+/// Wrap the hosts file in `# BEGIN EasyHosts` / `# END EasyHosts` markers.
+/// From then on saves only rewrite the lines between the markers.
+/// With `adopt_existing`, the current entries move into the managed block;
+/// otherwise an empty block is appended and they stay foreign.
+pub fn migrate_to_managed_region(adopt_existing: bool, max_aliases: usize) -> Result<HostsData, AppError> {
+    let _lock = lock_hosts()?;
+    let decoded = read_hosts_decoded()?;
+    let mut document = HostsDocument::parse(&decoded.text);
+    if !document.enable_managed_region(adopt_existing) {
+        return Err(AppError::new(ErrorCode::AlreadyManaged, "Hosts file already has an EasyHosts managed block"));
+    }
 
-    fs::write(&path, content)
-        .map_err(|e| format!("Failed to write hosts file: {}", e))
+    backup_hosts_file(BackupReason::Migrate, None)?;
+    write_hosts_document(&document, &decoded.format(document.newline()))?;
+
+    read_hosts(max_aliases)
+}
+
+/// Delete a section together with its entries
+pub fn delete_section(id: &str, max_aliases: usize) -> Result<HostsData, AppError> {
+    update_hosts_file(max_aliases, |data| {
+        let idx = section_index(data, id)?;
+        data.sections.remove(idx);
+        data.entries.retain(|e| e.section.as_deref() != Some(id));
+        Ok(())
+    })
 }
 
 /// Reset hosts file to Windows default
 #[cfg(target_os = "windows")]
-pub fn reset_hosts_to_default() -> Result<(), String> {
+pub fn reset_hosts_to_default() -> Result<(), AppError> {
+    let _lock = lock_hosts()?;
     let default_content = r#"# Copyright (c) Microsoft Corp.
 #
 # This is a sample HOSTS file used by Microsoft TCP/IP for Windows.
@@ -188,74 +435,50 @@ pub fn reset_hosts_to_default() -> Result<(), String> {
 "#;
 
     // Create backup first
-    backup_hosts_file()?;
+    backup_hosts_file(BackupReason::Reset, None)?;
 
-    let path = get_hosts_path();
-    fs::write(&path, default_content)
-        .map_err(|e| format!("Failed to reset hosts file: {}", e))
+    write_hosts_bytes(default_content.as_bytes(), default_content)?;
+    Ok(())
 }
 
 #[cfg(not(target_os = "windows"))]
-pub fn reset_hosts_to_default() -> Result<(), String> {
-    Err("Reset to default is only supported on Windows".to_string())
+pub fn reset_hosts_to_default() -> Result<(), AppError> {
+    Err(AppError::new(ErrorCode::Unsupported, "Reset to default is only supported on Windows"))
 }
 
-/// List all backup files
-pub fn list_backups() -> Result<Vec<BackupInfo>, String> {
-    let path = get_hosts_path();
-    let dir = path.parent()
-        .ok_or("Failed to get parent directory")?;
-
-    let mut backups = Vec::new();
-
-    let entries = fs::read_dir(dir)
-        .map_err(|e| format!("Failed to read directory: {}", e))?;
-
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let filename = entry.file_name().to_string_lossy().to_string();
-
-        if filename.starts_with("hosts.bak_") {
-            let metadata = entry.metadata()
-                .map_err(|e| format!("Failed to get metadata: {}", e))?;
-
-            // Extract timestamp from filename (hosts.bak_YYYYMMDD_HHMMSS)
-            let timestamp = filename.replace("hosts.bak_", "");
-
-            backups.push(BackupInfo {
-                filename,
-                path: entry.path().to_string_lossy().to_string(),
-                timestamp,
-                size: metadata.len(),
-            });
-        }
-    }
-
-    // Sort by timestamp descending (newest first)
-    backups.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-
-    Ok(backups)
+/// Backups of the hosts file in use, newest first
+pub fn list_backups() -> Result<Vec<BackupInfo>, AppError> {
+    // Listing may import old backups into the index
+    let _lock = lock_hosts()?;
+    backup::list(&get_hosts_path())
 }
 
-/// Restore hosts file from a backup
-pub fn restore_backup(backup_path: String) -> Result<(), String> {
-    // Create a backup of current state first
-    backup_hosts_file()?;
+/// Back up the hosts file now, optionally with a label
+pub fn create_backup(label: Option<&str>) -> Result<String, AppError> {
+    let _lock = lock_hosts()?;
+    backup_hosts_file(BackupReason::Manual, label)
+}
 
-    // Read backup content
-    let backup_content = fs::read_to_string(&backup_path)
-        .map_err(|e| format!("Failed to read backup file: {}", e))?;
+/// Restore hosts file from a backup, refusing one that is damaged
+pub fn restore_backup(id: &str) -> Result<(), AppError> {
+    let _lock = lock_hosts()?;
+    let backup_content = backup::read(&get_hosts_path(), id)?;
 
-    // Write to hosts file
-    let hosts_path = get_hosts_path();
-    fs::write(&hosts_path, backup_content)
-        .map_err(|e| format!("Failed to restore hosts file: {}", e))?;
+    // Create a backup of current state first
+    backup_hosts_file(BackupReason::Restore, None)?;
 
+    write_hosts_bytes(&backup_content, &encoding::decode(&backup_content).text)?;
     Ok(())
 }
 
-/// Delete a backup file
-pub fn delete_backup(backup_path: String) -> Result<(), String> {
-    fs::remove_file(&backup_path)
-        .map_err(|e| format!("Failed to delete backup file: {}", e))
+/// Delete a backup of the hosts file in use
+pub fn delete_backup(id: &str) -> Result<(), AppError> {
+    let _lock = lock_hosts()?;
+    backup::delete(&get_hosts_path(), id)
+}
+
+/// Label a backup, or clear its label with `None`
+pub fn set_backup_label(id: &str, label: Option<&str>) -> Result<BackupMeta, AppError> {
+    let _lock = lock_hosts()?;
+    backup::set_label(&get_hosts_path(), id, label)
 }
@@ -1,3 +1,4 @@
+use crate::error::{AppError, ErrorCode};
 use crate::models::HostsData;
 use serde::{Deserialize, Serialize};
 
@@ -9,7 +10,7 @@ pub struct ExportData {
 }
 
 /// Export hosts data to JSON format
-pub fn export_to_json(data: &HostsData) -> Result<String, String> {
+pub fn export_to_json(data: &HostsData) -> Result<String, AppError> {
     let export = ExportData {
         version: 1,
         timestamp: chrono::Local::now().to_rfc3339(),
@@ -17,23 +18,27 @@ pub fn export_to_json(data: &HostsData) -> Result<String, String> {
     };
     
     serde_json::to_string_pretty(&export)
-        .map_err(|e| format!("Failed to serialize to JSON: {}", e))
+        .map_err(|e| AppError::new(ErrorCode::Other, format!("Failed to serialize to JSON: {}", e)))
 }
 
 /// Import hosts data from JSON format
-pub fn import_from_json(json_str: &str) -> Result<HostsData, String> {
-    let export: ExportData = serde_json::from_str(json_str)
-        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+pub fn import_from_json(json_str: &str) -> Result<HostsData, AppError> {
+    let export: ExportData = serde_json::from_str(json_str).map_err(|e| {
+        AppError::new(ErrorCode::InvalidData, format!("Failed to parse JSON: {}", e)).with_line(e.line())
+    })?;
     
     // Version check (for future compatibility)
     if export.version > 1 {
-        return Err(format!("Unsupported version: {}. Please update the application.", export.version));
+        return Err(AppError::new(
+            ErrorCode::UnsupportedVersion,
+            format!("Unsupported version: {}. Please update the application.", export.version),
+        ));
     }
     
     Ok(export.hosts_data)
 }
 
 /// Export hosts data to hosts file format
-pub fn export_to_hosts(data: &HostsData) -> String {
-    crate::hosts::serialize_hosts(data)
+pub fn export_to_hosts(data: &HostsData, max_aliases: usize) -> String {
+    crate::hosts::serialize_hosts(data, max_aliases)
 }
@@ -1,33 +1,172 @@
 mod models;
+mod error;
+mod document;
 mod hosts;
 mod ping;
 mod permissions;
 mod import_export;
 mod dns;
 mod network;
+mod lint;
+mod validation;
+mod encoding;
+mod annotations;
+mod diff;
+mod format;
+mod atomic;
+mod revision;
+mod merge;
+mod lock;
+mod apply;
+mod target;
+mod protect;
+mod probe;
+mod backup;
 
-use models::{HostsData, PingResult};
-use hosts::BackupInfo;
+use models::{FileFormat, HostEntry, HostsData, PingResult};
+use error::AppError;
+use document::DEFAULT_MAX_ALIASES;
+use backup::{BackupInfo, BackupMeta, RetentionPolicy};
+use apply::{ApplyOptions, ApplyReport, Change, TargetResult};
+use target::{ActivePath, TargetConfig, TargetStatus};
+use protect::ProtectionConfig;
+use lint::{Diagnostic, LintConfig};
+use format::{FormatOptions, Formatted};
+use validation::EntryError;
+use revision::Revision;
+use merge::{MergeResult, Side};
+use diff::HostsDiff;
+use std::collections::HashMap;
 use tauri::tray::{TrayIconBuilder, TrayIconEvent};
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
+
+/// Names per hosts line requested by the UI, or the Windows limit.
+/// `0` disables splitting long entries.
+fn alias_limit(max_aliases: Option<usize>) -> usize {
+    max_aliases.unwrap_or(DEFAULT_MAX_ALIASES)
+}
 
 // Tauri commands
 
 #[tauri::command]
-fn read_hosts() -> Result<HostsData, String> {
-    let content = hosts::read_hosts_file()?;
-    Ok(hosts::parse_hosts(&content))
+fn read_hosts(max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::read_hosts(alias_limit(max_aliases)))
+}
+
+/// Save edited data, verify it and roll back if a step fails.
+/// Fails with a conflict if another program changed the file since
+/// `data.revision` was read, and lists the problems per entry when added
+/// or edited entries are invalid.
+#[tauri::command]
+fn save_hosts(
+    data: HostsData,
+    options: Option<ApplyOptions>,
+    max_aliases: Option<usize>,
+    target: Option<String>,
+) -> Result<ApplyReport, AppError> {
+    target::within(target.as_deref(), || {
+        apply::apply_hosts(&data, &options.unwrap_or_default(), alias_limit(max_aliases))
+    })
+}
+
+/// Apply the same changes to several targets, each as its own transaction.
+/// DNS flushing and verification only apply to the system hosts file.
+#[tauri::command]
+fn apply_to_targets(
+    targets: Vec<String>,
+    changes: Vec<Change>,
+    options: Option<ApplyOptions>,
+    max_aliases: Option<usize>,
+) -> Vec<TargetResult> {
+    apply::apply_to_targets(&targets, &changes, &options.unwrap_or_default(), alias_limit(max_aliases))
+}
+
+/// Show what saving `data` would change in the hosts file, entry by entry
+/// and as a text diff, without writing anything
+#[tauri::command]
+fn preview_save(data: HostsData, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsDiff, AppError> {
+    target::within(target.as_deref(), || hosts::preview_save(&data, alias_limit(max_aliases)))
+}
+
+/// Merge edits rejected as stale with the current file. `resolutions`
+/// picks a side for conflicting entries by id.
+#[tauri::command]
+fn merge_hosts(
+    data: HostsData,
+    resolutions: Option<HashMap<String, Side>>,
+    max_aliases: Option<usize>,
+    target: Option<String>,
+) -> Result<MergeResult, AppError> {
+    target::within(target.as_deref(), || {
+        hosts::merge_hosts(&data, &resolutions.unwrap_or_default(), alias_limit(max_aliases))
+    })
+}
+
+/// How long changes wait for another EasyHosts process to finish
+/// before failing
+#[tauri::command]
+fn set_lock_timeout(timeout_ms: u64) {
+    lock::set_timeout(timeout_ms);
+}
+
+/// Set which system entries saves may not drop or redirect
+/// (see `ApplyOptions::allow_protected`)
+#[tauri::command]
+fn set_protected_entries(config: ProtectionConfig) -> Result<(), AppError> {
+    protect::set_policy(config)
+}
+
+/// Check every entry without saving; returns one error per problem
+#[tauri::command]
+fn validate_hosts(data: HostsData) -> Vec<EntryError> {
+    validation::validate_hosts_data(&data).err().unwrap_or_default()
+}
+
+/// Rewrite the hosts file in another encoding or line ending style.
+/// Saves otherwise keep the format the file was read in.
+#[tauri::command]
+fn convert_hosts_format(format: FileFormat, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::convert_hosts_format(format, alias_limit(max_aliases)))
+}
+
+#[tauri::command]
+fn toggle_entry(id: String, enabled: bool, revision: Revision, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::set_entry_enabled(&id, enabled, &revision, alias_limit(max_aliases)))
+}
+
+#[tauri::command]
+fn update_entry(id: String, entry: HostEntry, revision: Revision, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::update_entry(&id, entry, &revision, alias_limit(max_aliases)))
+}
+
+#[tauri::command]
+fn delete_entry(id: String, revision: Revision, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::delete_entry(&id, &revision, alias_limit(max_aliases)))
 }
 
 #[tauri::command]
-fn save_hosts(data: HostsData) -> Result<String, String> {
-    // Create backup first
-    let backup_path = hosts::backup_hosts_file()?;
+fn toggle_section(id: String, enabled: bool, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::set_section_enabled(&id, enabled, alias_limit(max_aliases)))
+}
 
-    // Save new content
-    hosts::save_hosts_file(&data)?;
+#[tauri::command]
+fn rename_section(id: String, title: String, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::rename_section(&id, &title, alias_limit(max_aliases)))
+}
 
-    Ok(backup_path)
+#[tauri::command]
+fn move_section(id: String, index: usize, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::move_section(&id, index, alias_limit(max_aliases)))
+}
+
+#[tauri::command]
+fn delete_section(id: String, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::delete_section(&id, alias_limit(max_aliases)))
+}
+
+#[tauri::command]
+fn migrate_to_managed_region(adopt_existing: bool, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::migrate_to_managed_region(adopt_existing, alias_limit(max_aliases)))
 }
 
 #[tauri::command]
@@ -36,84 +175,194 @@ fn ping_host(domain: String) -> PingResult {
 }
 
 #[tauri::command]
-fn get_hosts_path() -> String {
-    hosts::get_hosts_path().to_string_lossy().to_string()
+fn get_hosts_path(target: Option<String>) -> Result<String, AppError> {
+    target::within(target.as_deref(), || Ok(hosts::get_hosts_path().to_string_lossy().to_string()))
+}
+
+/// The hosts file in use and whether it comes from the default, the
+/// setting, `EASYHOSTS_HOSTS_PATH` or `--hosts-path`
+#[tauri::command]
+fn get_hosts_target() -> ActivePath {
+    target::active_path()
+}
+
+/// Point EasyHosts at another hosts file or system root; `None` goes back
+/// to the system hosts file. The environment variable and command line
+/// flag take precedence.
+#[tauri::command]
+fn set_hosts_path(path: Option<String>) -> Result<ActivePath, AppError> {
+    target::set_setting(path.as_deref())
+}
+
+/// Register the named hosts targets, replacing those registered before.
+/// Commands take a target id to work on one of them.
+#[tauri::command]
+fn set_targets(targets: Vec<TargetConfig>) -> Result<Vec<TargetStatus>, AppError> {
+    target::set_targets(targets)
+}
+
+/// Registered targets with their path, backup directory and permissions
+#[tauri::command]
+fn list_targets() -> Result<Vec<TargetStatus>, AppError> {
+    target::list_targets()
+}
+
+#[tauri::command]
+fn check_admin(target: Option<String>) -> bool {
+    target::within(target.as_deref(), || Ok::<_, AppError>(permissions::check_admin())).unwrap_or(false)
 }
 
+/// Why the hosts file can't be written, if it can't
 #[tauri::command]
-fn check_admin() -> bool {
-    permissions::check_admin()
+fn probe_hosts_write(target: Option<String>) -> Result<probe::WriteDiagnosis, AppError> {
+    target::within(target.as_deref(), || Ok(probe::probe_write(&hosts::get_hosts_path())))
 }
 
 #[tauri::command]
-fn request_elevation() -> Result<(), String> {
+fn request_elevation() -> Result<(), AppError> {
     permissions::request_elevation()
 }
 
 #[tauri::command]
-fn export_to_json(data: HostsData) -> Result<String, String> {
+fn export_to_json(data: HostsData) -> Result<String, AppError> {
     import_export::export_to_json(&data)
 }
 
 #[tauri::command]
-fn import_from_json(json_str: String) -> Result<HostsData, String> {
+fn import_from_json(json_str: String) -> Result<HostsData, AppError> {
     import_export::import_from_json(&json_str)
 }
 
 #[tauri::command]
-fn export_to_hosts_format(data: HostsData) -> String {
-    import_export::export_to_hosts(&data)
+fn export_to_hosts_format(data: HostsData, max_aliases: Option<usize>) -> String {
+    import_export::export_to_hosts(&data, alias_limit(max_aliases))
 }
 
 /// Parse arbitrary hosts file content into structured data.
 /// Used by the raw edit mode and remote source import.
 #[tauri::command]
-fn parse_hosts_text(content: String) -> HostsData {
-    hosts::parse_hosts(&content)
+fn parse_hosts_text(content: String, max_aliases: Option<usize>) -> HostsData {
+    hosts::parse_hosts(&content, alias_limit(max_aliases))
+}
+
+/// Check hosts file content (the current hosts file if none is given) and
+/// report problems by line and column. Rules not mentioned in `config` run
+/// with their default severity.
+#[tauri::command]
+fn lint_hosts(content: Option<String>, config: Option<LintConfig>, target: Option<String>) -> Result<Vec<Diagnostic>, AppError> {
+    let content = match content {
+        Some(content) => content,
+        None => target::within(target.as_deref(), hosts::read_hosts_decoded)?.text,
+    };
+    Ok(lint::lint_hosts(&content, &config.unwrap_or_default()))
+}
+
+/// Format hosts file content (the current hosts file if none is given)
+/// and return the result with a diff, without saving anything
+#[tauri::command]
+fn format_hosts(
+    content: Option<String>,
+    options: Option<FormatOptions>,
+    max_aliases: Option<usize>,
+    target: Option<String>,
+) -> Result<Formatted, AppError> {
+    let content = match content {
+        Some(content) => content,
+        None => target::within(target.as_deref(), hosts::read_hosts_decoded)?.text,
+    };
+    Ok(format::format_hosts(&content, &options.unwrap_or_default(), alias_limit(max_aliases)))
 }
 
+/// Format the hosts file and save it
 #[tauri::command]
-fn reset_hosts_to_default() -> Result<(), String> {
-    hosts::reset_hosts_to_default()
+fn save_formatted_hosts(options: Option<FormatOptions>, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsData, AppError> {
+    target::within(target.as_deref(), || hosts::format_hosts_file(&options.unwrap_or_default(), alias_limit(max_aliases)))
 }
 
 #[tauri::command]
-fn lookup_dns(domain: String) -> Result<String, String> {
+fn reset_hosts_to_default(target: Option<String>) -> Result<(), AppError> {
+    target::within(target.as_deref(), hosts::reset_hosts_to_default)
+}
+
+#[tauri::command]
+fn lookup_dns(domain: String) -> Result<String, AppError> {
     dns::lookup_domain(&domain)
 }
 
 #[tauri::command]
-fn flush_dns_cache() -> Result<(), String> {
+fn flush_dns_cache() -> Result<(), AppError> {
     dns::flush_dns_cache()
 }
 
 #[tauri::command]
-fn get_current_ssid() -> Result<Option<String>, String> {
+fn get_current_ssid() -> Result<Option<String>, AppError> {
     network::current_ssid()
 }
 
+/// Set which backups are kept after each new one
+#[tauri::command]
+fn set_backup_retention(policy: RetentionPolicy) -> Result<(), AppError> {
+    backup::set_retention(policy)
+}
+
+/// Set the profile recorded with new backups
 #[tauri::command]
-fn list_backups() -> Result<Vec<BackupInfo>, String> {
-    hosts::list_backups()
+fn set_active_profile(name: Option<String>) {
+    backup::set_profile(name);
 }
 
+/// Back up the hosts file now, optionally with a label that keeps it from being pruned
 #[tauri::command]
-fn restore_backup(backup_path: String) -> Result<(), String> {
-    hosts::restore_backup(backup_path)
+fn create_backup(label: Option<String>, target: Option<String>) -> Result<String, AppError> {
+    target::within(target.as_deref(), || hosts::create_backup(label.as_deref()))
 }
 
 #[tauri::command]
-fn delete_backup(backup_path: String) -> Result<(), String> {
-    hosts::delete_backup(backup_path)
+fn list_backups(target: Option<String>) -> Result<Vec<BackupInfo>, AppError> {
+    target::within(target.as_deref(), hosts::list_backups)
+}
+
+/// Show what restoring a backup would change
+#[tauri::command]
+fn preview_restore(id: String, max_aliases: Option<usize>, target: Option<String>) -> Result<HostsDiff, AppError> {
+    target::within(target.as_deref(), || hosts::preview_restore(&id, alias_limit(max_aliases)))
+}
+
+#[tauri::command]
+fn restore_backup(id: String, target: Option<String>) -> Result<(), AppError> {
+    target::within(target.as_deref(), || hosts::restore_backup(&id))
+}
+
+#[tauri::command]
+fn delete_backup(id: String, target: Option<String>) -> Result<(), AppError> {
+    target::within(target.as_deref(), || hosts::delete_backup(&id))
+}
+
+#[tauri::command]
+fn set_backup_label(id: String, label: Option<String>, target: Option<String>) -> Result<BackupMeta, AppError> {
+    target::within(target.as_deref(), || hosts::set_backup_label(&id, label.as_deref()))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Refuse to start on a bad --hosts-path or EASYHOSTS_HOSTS_PATH rather
+    // than edit the wrong file
+    if let Err(e) = target::init_from_args(std::env::args().skip(1)).and_then(|_| target::init_from_env()) {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .setup(|app| {
+            // Backups live in the app data directory, not next to the hosts file
+            match app.path().app_data_dir() {
+                Ok(dir) => backup::set_root(dir.join("backups")),
+                Err(err) => eprintln!("Failed to locate app data directory: {}", err),
+            }
+
             // System tray with quick profile cycling (desktop only)
             #[cfg(desktop)]
             {
@@ -139,21 +388,49 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             read_hosts,
             save_hosts,
+            merge_hosts,
+            preview_save,
+            set_lock_timeout,
+            set_protected_entries,
+            validate_hosts,
+            convert_hosts_format,
+            toggle_entry,
+            update_entry,
+            delete_entry,
+            toggle_section,
+            rename_section,
+            move_section,
+            delete_section,
+            migrate_to_managed_region,
             ping_host,
             get_hosts_path,
+            get_hosts_target,
+            set_hosts_path,
+            set_targets,
+            list_targets,
+            apply_to_targets,
             check_admin,
+            probe_hosts_write,
             request_elevation,
             export_to_json,
             import_from_json,
             export_to_hosts_format,
             parse_hosts_text,
+            lint_hosts,
+            format_hosts,
+            save_formatted_hosts,
             reset_hosts_to_default,
             lookup_dns,
             flush_dns_cache,
             get_current_ssid,
+            set_backup_retention,
+            set_active_profile,
+            create_backup,
             list_backups,
+            preview_restore,
             restore_backup,
-            delete_backup
+            delete_backup,
+            set_backup_label
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
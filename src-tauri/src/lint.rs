@@ -0,0 +1,349 @@
+//! Rule-based checks for hosts files.
+//!
+//! Each rule reports diagnostics with a 1-based line and column so the UI
+//! can point at the exact token. Rules can be switched off or given a
+//! different severity through `LintConfig`.
+
+use crate::document::{EntryLine, HostsDocument, LineEnding, LineKind, DEFAULT_MAX_ALIASES};
+use crate::validation::{self, HostIp};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+pub const RULE_INVALID_IP: &str = "invalid-ip";
+pub const RULE_INVALID_HOSTNAME: &str = "invalid-hostname";
+pub const RULE_MALFORMED_LINE: &str = "malformed-line";
+pub const RULE_LINE_TOO_LONG: &str = "line-too-long";
+pub const RULE_DUPLICATE_ALIAS: &str = "duplicate-alias";
+pub const RULE_SHADOWED_ENTRY: &str = "shadowed-entry";
+pub const RULE_TOO_MANY_ALIASES: &str = "too-many-aliases";
+
+/// Rule ids with their default severity
+const RULES: &[(&str, Severity)] = &[
+    (RULE_INVALID_IP, Severity::Error),
+    (RULE_INVALID_HOSTNAME, Severity::Error),
+    (RULE_MALFORMED_LINE, Severity::Error),
+    (RULE_LINE_TOO_LONG, Severity::Warning),
+    (RULE_DUPLICATE_ALIAS, Severity::Warning),
+    (RULE_SHADOWED_ENTRY, Severity::Warning),
+    (RULE_TOO_MANY_ALIASES, Severity::Warning),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Per-rule setting in `LintConfig`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Overrides keyed by rule id; rules not listed use their default
+    pub rules: HashMap<String, RuleLevel>,
+    pub max_line_length: usize,
+    /// Windows ignores hostnames past the ninth on a line
+    pub max_aliases: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            rules: HashMap::new(),
+            max_line_length: 255,
+            max_aliases: DEFAULT_MAX_ALIASES,
+        }
+    }
+}
+
+impl LintConfig {
+    fn severity(&self, rule: &str) -> Option<Severity> {
+        match self.rules.get(rule) {
+            Some(RuleLevel::Off) => None,
+            Some(RuleLevel::Info) => Some(Severity::Info),
+            Some(RuleLevel::Warning) => Some(Severity::Warning),
+            Some(RuleLevel::Error) => Some(Severity::Error),
+            None => RULES.iter().find(|(id, _)| *id == rule).map(|(_, s)| *s),
+        }
+    }
+}
+
+/// Suggested fix for a diagnostic
+#[derive(Debug, Clone, Serialize)]
+pub struct Fix {
+    pub description: String,
+    /// Replacement for the whole line; `None` removes the line
+    pub replacement: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, counted in characters
+    pub column: usize,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+/// Lint hosts file content, as decoded by `encoding::decode`. A byte
+/// order mark left at the start of the text is ignored.
+pub fn lint_hosts(content: &str, config: &LintConfig) -> Vec<Diagnostic> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let document = HostsDocument::parse(content);
+    let mut linter = Linter {
+        config,
+        newline: document.newline(),
+        diagnostics: Vec::new(),
+        mapped: HashMap::new(),
+    };
+
+    for (idx, line) in document.lines().iter().enumerate() {
+        let line_no = idx + 1;
+        let text = line.text();
+
+        let length = text.chars().count();
+        if length > config.max_line_length {
+            linter.report(
+                RULE_LINE_TOO_LONG,
+                line_no,
+                config.max_line_length + 1,
+                format!("Line is {} characters long (limit {})", length, config.max_line_length),
+                None,
+            );
+        }
+
+        match &line.kind {
+            LineKind::Unknown => {
+                let column = text.chars().take_while(|c| c.is_whitespace()).count() + 1;
+                linter.report(
+                    RULE_MALFORMED_LINE,
+                    line_no,
+                    column,
+                    "Line is not a valid entry: expected an IP address followed by at least one hostname".to_string(),
+                    Some(Fix {
+                        description: "Comment out the line".to_string(),
+                        replacement: Some(format!("# {}", text.trim())),
+                    }),
+                );
+            }
+            LineKind::Entry(entry) => linter.lint_entry(line_no, entry),
+            _ => {}
+        }
+    }
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    /// Line ending of the document, used between lines a fix splits
+    newline: LineEnding,
+    diagnostics: Vec<Diagnostic>,
+    /// First active line mapping each (hostname, is IPv6)
+    mapped: HashMap<(String, bool), usize>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: &'static str, line: usize, column: usize, message: String, fix: Option<Fix>) {
+        if let Some(severity) = self.config.severity(rule) {
+            self.diagnostics.push(Diagnostic {
+                rule,
+                severity,
+                line,
+                column,
+                message,
+                fix,
+            });
+        }
+    }
+
+    fn lint_entry(&mut self, line_no: usize, entry: &EntryLine) {
+        let ip_column = entry.ip_column() + 1;
+        let columns: Vec<usize> = entry.domain_columns().iter().map(|c| c + 1).collect();
+
+        let ip = match entry.ip.parse::<HostIp>() {
+            Ok(ip) => Some(ip.addr),
+            Err(message) => {
+                self.report(RULE_INVALID_IP, line_no, ip_column, message, None);
+                None
+            }
+        };
+
+        for (i, (domain, column)) in entry.domains.iter().zip(&columns).enumerate() {
+            let Err(reason) = validation::check_hostname(domain) else {
+                continue;
+            };
+
+            // Internationalized names only resolve in their punycode form
+            match validation::hostname_to_ascii(domain) {
+                Ok(ascii) if !domain.is_ascii() => {
+                    let mut domains = entry.domains.clone();
+                    domains[i] = ascii.clone();
+                    self.report(
+                        RULE_INVALID_HOSTNAME,
+                        line_no,
+                        *column,
+                        format!("'{}' must be written as '{}' for the resolver to match it", domain, ascii),
+                        Some(Fix {
+                            description: "Convert to punycode".to_string(),
+                            replacement: Some(entry.render_with_domains(&domains)),
+                        }),
+                    );
+                }
+                _ => self.report(
+                    RULE_INVALID_HOSTNAME,
+                    line_no,
+                    *column,
+                    format!("'{}' is not a valid hostname: {}", domain, reason),
+                    None,
+                ),
+            }
+        }
+
+        // The same name twice on one line
+        let mut seen = HashSet::new();
+        let unique: Vec<String> = entry
+            .domains
+            .iter()
+            .filter(|d| seen.insert(d.to_lowercase()))
+            .cloned()
+            .collect();
+        if unique.len() < entry.domains.len() {
+            let mut seen = HashSet::new();
+            for (domain, column) in entry.domains.iter().zip(&columns) {
+                if !seen.insert(domain.to_lowercase()) {
+                    self.report(
+                        RULE_DUPLICATE_ALIAS,
+                        line_no,
+                        *column,
+                        format!("'{}' appears more than once on this line", domain),
+                        Some(Fix {
+                            description: "Remove repeated hostnames".to_string(),
+                            replacement: Some(entry.render_with_domains(&unique)),
+                        }),
+                    );
+                }
+            }
+        }
+
+        let max = self.config.max_aliases;
+        if max > 0 && entry.domains.len() > max {
+            let lines: Vec<String> = entry
+                .domains
+                .chunks(max)
+                .map(|chunk| entry.render_with_domains(chunk))
+                .collect();
+            self.report(
+                RULE_TOO_MANY_ALIASES,
+                line_no,
+                columns[max],
+                format!(
+                    "{} hostnames on one line; Windows ignores those after the first {}",
+                    entry.domains.len(),
+                    max
+                ),
+                Some(Fix {
+                    description: format!("Split into lines of at most {} hostnames", max),
+                    replacement: Some(lines.join(self.newline.as_str())),
+                }),
+            );
+        }
+
+        // Only lines in effect can shadow or be shadowed
+        let Some(ip) = ip.filter(|_| entry.is_active()) else {
+            return;
+        };
+
+        let mut shadowed = Vec::new();
+        for (i, domain) in unique.iter().enumerate() {
+            let key = (domain.to_lowercase(), ip.is_ipv6());
+            match self.mapped.get(&key) {
+                Some(&first) => shadowed.push((i, first)),
+                None => {
+                    self.mapped.insert(key, line_no);
+                }
+            }
+        }
+
+        if shadowed.is_empty() {
+            return;
+        }
+
+        if shadowed.len() == unique.len() {
+            let (_, first) = shadowed[0];
+            self.report(
+                RULE_SHADOWED_ENTRY,
+                line_no,
+                ip_column,
+                format!(
+                    "Entry is never used: all of its hostnames are already mapped by earlier lines (first on line {})",
+                    first
+                ),
+                Some(Fix {
+                    description: "Remove the line".to_string(),
+                    replacement: None,
+                }),
+            );
+            return;
+        }
+
+        let skip: HashSet<usize> = shadowed.iter().map(|(i, _)| *i).collect();
+        let remaining: Vec<String> = unique
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !skip.contains(i))
+            .map(|(_, d)| d.clone())
+            .collect();
+
+        for (i, first) in shadowed {
+            let domain = &unique[i];
+            let column = entry
+                .domains
+                .iter()
+                .position(|d| d == domain)
+                .map_or(ip_column, |pos| columns[pos]);
+            self.report(
+                RULE_SHADOWED_ENTRY,
+                line_no,
+                column,
+                format!("'{}' is already mapped on line {}; this mapping is never used", domain, first),
+                Some(Fix {
+                    description: "Remove shadowed hostnames".to_string(),
+                    replacement: Some(entry.render_with_domains(&remaining)),
+                }),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_fix_uses_the_files_line_ending() {
+        let config = LintConfig { max_aliases: 2, ..LintConfig::default() };
+        let diagnostics = lint_hosts("127.0.0.1 localhost\r\n10.0.0.1 a b c\r\n", &config);
+        let fix = diagnostics
+            .iter()
+            .find(|d| d.rule == RULE_TOO_MANY_ALIASES)
+            .and_then(|d| d.fix.as_ref())
+            .and_then(|f| f.replacement.as_deref());
+        assert_eq!(fix, Some("10.0.0.1 a b\r\n10.0.0.1 c"));
+    }
+}
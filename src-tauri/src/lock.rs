@@ -0,0 +1,72 @@
+//! Advisory lock serializing hosts file changes between processes.
+//!
+//! Two EasyHosts windows, or the app and a script, must not interleave their
+//! read-backup-write sequences. Each sequence holds an exclusive lock on a
+//! lock file in the app data directory, one per hosts file (`flock` on
+//! Unix, `LockFileEx` on Windows), until it is done. The lock is released
+//! when the guard is dropped, or by the OS if the process dies.
+
+use crate::error::{AppError, ErrorCode};
+use std::fs::{self, File, OpenOptions};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_LOCK_TIMEOUT_MS: u64 = 5000;
+
+const LOCK_FILE_NAME: &str = ".easyhosts.lock";
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long to wait for another process to finish, set from the UI
+static LOCK_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_LOCK_TIMEOUT_MS);
+
+pub fn set_timeout(timeout_ms: u64) {
+    LOCK_TIMEOUT_MS.store(timeout_ms, Ordering::Relaxed);
+}
+
+/// Held while the hosts file is being changed
+#[derive(Debug)]
+pub struct HostsLock {
+    file: File,
+}
+
+impl Drop for HostsLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// Take the lock in `dir`, waiting up to the configured timeout.
+/// Must not be called again while the same process holds the lock.
+pub fn acquire(dir: &Path) -> Result<HostsLock, AppError> {
+    fs::create_dir_all(dir)
+        .map_err(|e| AppError::io(ErrorCode::WriteFailed, "create lock directory", dir, &e))?;
+    let path = dir.join(LOCK_FILE_NAME);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| AppError::io(ErrorCode::WriteFailed, "open lock file", &path, &e))?;
+
+    let deadline = Instant::now() + Duration::from_millis(LOCK_TIMEOUT_MS.load(Ordering::Relaxed));
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(HostsLock { file }),
+            Err(std::fs::TryLockError::WouldBlock) if Instant::now() < deadline => {
+                thread::sleep(RETRY_INTERVAL);
+            }
+            Err(std::fs::TryLockError::WouldBlock) => {
+                return Err(AppError::new(
+                    ErrorCode::Locked,
+                    "Another EasyHosts process is applying changes to the hosts file; try again once it has finished",
+                )
+                .with_path(&path));
+            }
+            Err(std::fs::TryLockError::Error(e)) => {
+                return Err(AppError::io(ErrorCode::WriteFailed, "lock", &path, &e));
+            }
+        }
+    }
+}
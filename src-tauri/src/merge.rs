@@ -0,0 +1,236 @@
+//! Three-way merge of edits made in the UI with changes made on disk.
+//!
+//! Entries are matched by id across the version that was read (base), the
+//! edited version (ours) and the file as it is now (theirs), which the caller
+//! gives the ids of base (see `document::carry_entry_ids`). A change made
+//! on only one side is taken as is; an entry changed differently on both
+//! sides is a conflict, left as it is on disk until the UI picks a side.
+
+use crate::models::{HostEntry, HostsData};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Which version of a conflicting entry to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+/// An entry changed both in the UI and on disk. A missing candidate means
+/// that side deleted the entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeConflict {
+    pub id: String,
+    /// First hostname of the entry, for display
+    pub domain: String,
+    pub base: Option<HostEntry>,
+    pub ours: Option<HostEntry>,
+    pub theirs: Option<HostEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeResult {
+    /// Merged data, carrying the revision on disk so it can be saved.
+    /// Unresolved conflicts keep the version on disk.
+    pub data: HostsData,
+    pub conflicts: Vec<MergeConflict>,
+    /// Number of our changes carried over
+    pub applied: usize,
+}
+
+/// Merge `ours` and `theirs`, both derived from `base`. `resolutions`
+/// picks a side for conflicts by entry id.
+pub fn merge(
+    base: &HostsData,
+    ours: &HostsData,
+    theirs: &HostsData,
+    resolutions: &HashMap<String, Side>,
+) -> MergeResult {
+    let base_entries = by_id(&base.entries);
+    let our_entries = by_id(&ours.entries);
+    let their_entries = by_id(&theirs.entries);
+
+    // Ids whose result differs from disk, with the entry to keep (`None` deletes it)
+    let mut taken: HashMap<&str, Option<&HostEntry>> = HashMap::new();
+    let mut conflicts = Vec::new();
+
+    let ids = theirs.entries.iter().chain(&ours.entries).chain(&base.entries).map(|e| e.id.as_str());
+    for id in ids.filter(|id| !id.is_empty()) {
+        if taken.contains_key(id) || conflicts.iter().any(|c: &MergeConflict| c.id == id) {
+            continue;
+        }
+
+        let (b, o, t) = (base_entries.get(id).copied(), our_entries.get(id).copied(), their_entries.get(id).copied());
+        if o == b || o == t {
+            continue;
+        }
+
+        if t == b || resolutions.get(id) == Some(&Side::Ours) {
+            taken.insert(id, o);
+        } else if resolutions.get(id) != Some(&Side::Theirs) {
+            conflicts.push(MergeConflict {
+                id: id.to_string(),
+                domain: [o, t, b].into_iter().flatten().find_map(|e| e.domains.first().cloned()).unwrap_or_default(),
+                base: b.cloned(),
+                ours: o.cloned(),
+                theirs: t.cloned(),
+            });
+        }
+    }
+
+    let mut entries: Vec<HostEntry> = theirs
+        .entries
+        .iter()
+        .filter_map(|e| match taken.get(e.id.as_str()) {
+            Some(kept) => kept.cloned(),
+            None => Some(e.clone()),
+        })
+        .collect();
+
+    // Entries added in the UI go after the entry they followed there
+    for (pos, entry) in ours.entries.iter().enumerate() {
+        let added = entry.id.is_empty()
+            || (!their_entries.contains_key(entry.id.as_str()) && taken.contains_key(entry.id.as_str()));
+        if !added {
+            continue;
+        }
+
+        let at = ours.entries[..pos]
+            .iter()
+            .rev()
+            .find_map(|prev| entries.iter().position(|e| same_entry(e, prev)))
+            .map_or(0, |i| i + 1);
+        entries.insert(at, entry.clone());
+    }
+
+    let applied = taken.len() + ours.entries.iter().filter(|e| e.id.is_empty()).count();
+
+    // Sections are taken from the UI unless they were changed on disk too
+    let sections = if theirs.sections == base.sections {
+        ours.sections.clone()
+    } else {
+        theirs.sections.clone()
+    };
+
+    MergeResult {
+        data: HostsData {
+            entries,
+            sections,
+            ..theirs.clone()
+        },
+        conflicts,
+        applied,
+    }
+}
+
+fn by_id(entries: &[HostEntry]) -> HashMap<&str, &HostEntry> {
+    entries
+        .iter()
+        .filter(|e| !e.id.is_empty())
+        .map(|e| (e.id.as_str(), e))
+        .collect()
+}
+
+/// Entries without an id (added in the UI) can only be matched by value
+fn same_entry(a: &HostEntry, b: &HostEntry) -> bool {
+    if b.id.is_empty() {
+        a == b
+    } else {
+        a.id == b.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::HostsDocument;
+
+    fn data(content: &str) -> HostsData {
+        HostsDocument::parse(content).to_hosts_data()
+    }
+
+    fn lines(data: &HostsData) -> Vec<String> {
+        data.entries.iter().map(|e| format!("{} {}", e.ip, e.domains.join(" "))).collect()
+    }
+
+    const BASE: &str = "10.0.0.1 a.local\n10.0.0.2 b.local\n10.0.0.3 c.local\n";
+
+    #[test]
+    fn delete_against_edit_conflicts() {
+        let base = data(BASE);
+        let mut ours = base.clone();
+        ours.entries.remove(1);
+        let mut theirs = base.clone();
+        theirs.entries[1].ip = "10.0.0.20".to_string();
+
+        let result = merge(&base, &ours, &theirs, &HashMap::new());
+        assert_eq!(result.conflicts.len(), 1);
+        let conflict = &result.conflicts[0];
+        assert_eq!(conflict.domain, "b.local");
+        assert!(conflict.ours.is_none());
+        assert_eq!(conflict.theirs.as_ref().map(|e| e.ip.as_str()), Some("10.0.0.20"));
+        // Unresolved, the version on disk stays
+        assert_eq!(lines(&result.data), ["10.0.0.1 a.local", "10.0.0.20 b.local", "10.0.0.3 c.local"]);
+
+        let resolutions = HashMap::from([(conflict.id.clone(), Side::Ours)]);
+        let result = merge(&base, &ours, &theirs, &resolutions);
+        assert!(result.conflicts.is_empty());
+        assert_eq!(lines(&result.data), ["10.0.0.1 a.local", "10.0.0.3 c.local"]);
+    }
+
+    #[test]
+    fn same_change_on_both_sides_is_not_a_conflict() {
+        let base = data(BASE);
+        let mut ours = base.clone();
+        ours.entries[2].enabled = false;
+        let theirs = ours.clone();
+
+        let result = merge(&base, &ours, &theirs, &HashMap::new());
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.applied, 0);
+        assert!(!result.data.entries[2].enabled);
+    }
+
+    #[test]
+    fn new_entry_goes_after_its_predecessor() {
+        let base = data(BASE);
+        let mut ours = base.clone();
+        let mut added = ours.entries[0].clone();
+        added.id = String::new();
+        added.ip = "10.0.0.9".to_string();
+        added.domains = vec!["new.local".to_string()];
+        ours.entries.insert(1, added);
+        let theirs = data("10.0.0.1 a.local\n10.0.0.4 d.local\n10.0.0.2 b.local\n10.0.0.3 c.local\n");
+
+        let result = merge(&base, &ours, &theirs, &HashMap::new());
+        assert!(result.conflicts.is_empty());
+        assert_eq!(result.applied, 1);
+        assert_eq!(
+            lines(&result.data),
+            ["10.0.0.1 a.local", "10.0.0.9 new.local", "10.0.0.4 d.local", "10.0.0.2 b.local", "10.0.0.3 c.local"]
+        );
+    }
+
+    #[test]
+    fn section_changes_from_one_side_are_kept() {
+        let base = data("# === Dev ===\n10.0.0.1 a.local\n# === Lab ===\n10.0.0.2 b.local\n");
+        let mut ours = base.clone();
+        ours.sections[1].enabled = false;
+        let mut theirs = base.clone();
+        theirs.entries[0].section = Some(base.sections[1].id.clone());
+
+        let result = merge(&base, &ours, &theirs, &HashMap::new());
+        assert!(result.conflicts.is_empty());
+        assert!(result.data.sections[0].enabled);
+        assert!(!result.data.sections[1].enabled);
+        assert_eq!(result.data.entries[0].section, theirs.entries[0].section);
+
+        // Changed on disk as well, the sections on disk win
+        let mut theirs = base.clone();
+        theirs.sections[0].title = "Development".to_string();
+        let result = merge(&base, &ours, &theirs, &HashMap::new());
+        assert_eq!(result.data.sections, theirs.sections);
+    }
+}
@@ -1,15 +1,47 @@
+use crate::error::AppError;
+use crate::revision::Revision;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct HostEntry {
+    /// Derived from the first hostname, unique within the file. Stays the
+    /// same across reads and edits as long as the first hostname does.
+    #[serde(default)]
+    pub id: String,
     pub enabled: bool,
     pub ip: String,
     pub domains: Vec<String>,
     pub comment: Option<String>,
+    /// Id of the section the entry sits under
+    #[serde(default)]
+    pub section: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub meta: EntryMeta,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Metadata kept with an entry, written as `@key=value` annotations
+/// after its inline comment
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntryMeta {
+    /// When the entry was added (`@created=`)
+    pub created: Option<String>,
+    /// When the entry was last changed (`@modified=`)
+    pub modified: Option<String>,
+    /// Who added the entry (`@by=`)
+    pub author: Option<String>,
+    /// Free-form note (`@note=`)
+    pub note: Option<String>,
+    /// Related URL, e.g. a ticket (`@link=`)
+    pub link: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Section {
+    #[serde(default)]
+    pub id: String,
     pub title: String,
     pub enabled: bool,
 }
@@ -18,6 +50,55 @@ pub struct Section {
 pub struct HostsData {
     pub entries: Vec<HostEntry>,
     pub sections: Vec<Section>,
+    /// Present when the file has an EasyHosts managed block
+    #[serde(default)]
+    pub foreign: Option<ForeignContent>,
+    /// How the file is stored on disk; set when read from disk
+    #[serde(default)]
+    pub format: Option<FileFormat>,
+    /// 1-based lines whose text could not be decoded exactly
+    #[serde(default)]
+    pub lossy_lines: Vec<usize>,
+    /// Revision read from disk; saves are refused once the file has moved on
+    #[serde(default)]
+    pub revision: Option<Revision>,
+}
+
+/// Text encoding of the hosts file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16-le")]
+    Utf16Le,
+    #[serde(rename = "utf-16-be")]
+    Utf16Be,
+    #[serde(rename = "latin-1")]
+    Latin1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineStyle {
+    Lf,
+    Crlf,
+}
+
+/// Encoding, byte order mark and line endings of the hosts file.
+/// Saves write the file back the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFormat {
+    pub encoding: TextEncoding,
+    pub bom: bool,
+    pub line_ending: LineStyle,
+}
+
+/// Content outside the managed block, owned by other tools (read-only)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForeignContent {
+    pub before: String,
+    pub after: String,
+    pub entries: Vec<HostEntry>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,4 +106,6 @@ pub struct PingResult {
     pub success: bool,
     pub avg_rtt: Option<f64>,
     pub message: String,
+    /// Why the ping failed
+    pub error: Option<AppError>,
 }
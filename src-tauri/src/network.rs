@@ -1,9 +1,10 @@
+use crate::error::AppError;
 use std::process::Command;
 
 /// Best-effort retrieval of the current Wi-Fi SSID.
 /// Returns Ok(Some(ssid)) if detected, Ok(None) if not connected
 /// or detection failed gracefully, and Err on hard failures.
-pub fn current_ssid() -> Result<Option<String>, String> {
+pub fn current_ssid() -> Result<Option<String>, AppError> {
     #[cfg(target_os = "windows")]
     {
         return current_ssid_windows();
@@ -21,21 +22,25 @@ pub fn current_ssid() -> Result<Option<String>, String> {
 
     #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
     {
-        Err("SSID detection is not supported on this platform".to_string())
+        use crate::error::ErrorCode;
+
+        Err(AppError::new(ErrorCode::Unsupported, "SSID detection is not supported on this platform"))
     }
 }
 
 #[cfg(target_os = "windows")]
-fn current_ssid_windows() -> Result<Option<String>, String> {
+fn current_ssid_windows() -> Result<Option<String>, AppError> {
+    use crate::error::ErrorCode;
+
     let output = Command::new("netsh")
         .args(["wlan", "show", "interfaces"])
         .output()
-        .map_err(|e| format!("Failed to run netsh: {}", e))?;
+        .map_err(|e| AppError::command("netsh", &e))?;
 
     if !output.status.success() {
-        return Err(format!(
-            "netsh returned non-zero exit code: {}",
-            output.status
+        return Err(AppError::new(
+            ErrorCode::CommandFailed,
+            format!("netsh returned non-zero exit code: {}", output.status),
         ));
     }
 
@@ -57,7 +62,7 @@ fn current_ssid_windows() -> Result<Option<String>, String> {
 }
 
 #[cfg(target_os = "macos")]
-fn current_ssid_macos() -> Result<Option<String>, String> {
+fn current_ssid_macos() -> Result<Option<String>, AppError> {
     // Try the `airport` utility first
     let output = Command::new("/System/Library/PrivateFrameworks/Apple80211.framework/Versions/Current/Resources/airport")
         .arg("-I")
@@ -82,7 +87,7 @@ fn current_ssid_macos() -> Result<Option<String>, String> {
     let output = Command::new("networksetup")
         .args(["-getairportnetwork", "en0"])
         .output()
-        .map_err(|e| format!("Failed to run networksetup: {}", e))?;
+        .map_err(|e| AppError::command("networksetup", &e))?;
 
     if !output.status.success() {
         return Ok(None);
@@ -101,7 +106,7 @@ fn current_ssid_macos() -> Result<Option<String>, String> {
 }
 
 #[cfg(target_os = "linux")]
-fn current_ssid_linux() -> Result<Option<String>, String> {
+fn current_ssid_linux() -> Result<Option<String>, AppError> {
     // Try NetworkManager's nmcli
     if let Ok(output) = Command::new("nmcli")
         .args(["-t", "-f", "active,ssid", "dev", "wifi"])
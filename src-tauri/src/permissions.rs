@@ -1,4 +1,7 @@
-/// Check if the application is running with administrator/root privileges
+use crate::error::{AppError, ErrorCode};
+
+/// Check if the application is running with administrator/root privileges,
+/// or can otherwise write the hosts file in use
 pub fn check_admin() -> bool {
     #[cfg(target_os = "windows")]
     {
@@ -13,29 +16,49 @@ pub fn check_admin() -> bool {
 
 #[cfg(target_os = "windows")]
 fn check_admin_windows() -> bool {
-    // Try to open hosts file with write access
-    // If successful, we have admin privileges
-    let hosts_path = super::hosts::get_hosts_path();
-    
-    match std::fs::OpenOptions::new()
+    // If we can open the hosts file for writing, we have admin privileges
+    can_write_hosts(&super::hosts::get_hosts_path())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn check_admin_unix() -> bool {
+    // A hosts file outside the system (e.g. a container rootfs) may be
+    // writable without root
+    let root = unsafe { libc::geteuid() } == 0;
+    root || can_write_hosts(&super::hosts::get_hosts_path())
+}
+
+/// Whether the hosts file at `path` can be replaced by this process
+#[cfg(target_os = "windows")]
+pub fn can_write_hosts(path: &std::path::Path) -> bool {
+    std::fs::OpenOptions::new()
         .write(true)
         .append(true)
-        .open(&hosts_path)
-    {
-        Ok(_) => true,
-        Err(_) => false,
-    }
+        .open(path)
+        .is_ok()
 }
 
+/// Whether the hosts file at `path` can be replaced by this process
 #[cfg(not(target_os = "windows"))]
-fn check_admin_unix() -> bool {
-    unsafe {
-        libc::geteuid() == 0
-    }
+pub fn can_write_hosts(path: &std::path::Path) -> bool {
+    let Some(dir) = path.parent() else {
+        return false;
+    };
+    (!path.exists() || writable(path)) && writable(dir)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn writable(path: &std::path::Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
 }
 
 /// Request elevation by restarting the application with admin privileges
-pub fn request_elevation() -> Result<(), String> {
+pub fn request_elevation() -> Result<(), AppError> {
     #[cfg(target_os = "windows")]
     {
         request_elevation_windows()
@@ -43,18 +66,21 @@ pub fn request_elevation() -> Result<(), String> {
     
     #[cfg(not(target_os = "windows"))]
     {
-        Err("Elevation request not implemented for this platform. Please restart manually with sudo.".to_string())
+        Err(AppError::new(
+            ErrorCode::Unsupported,
+            "Elevation request not implemented for this platform. Please restart manually with sudo.",
+        ))
     }
 }
 
 #[cfg(target_os = "windows")]
-fn request_elevation_windows() -> Result<(), String> {
+fn request_elevation_windows() -> Result<(), AppError> {
     use std::process::Command;
     use std::env;
     
     // Get the current executable path
     let exe_path = env::current_exe()
-        .map_err(|e| format!("Failed to get executable path: {}", e))?;
+        .map_err(|e| AppError::new(ErrorCode::Other, format!("Failed to get executable path: {}", e)).with_os_error(&e))?;
     
     // Use PowerShell Start-Process with -Verb RunAs to request UAC elevation
     let _status = Command::new("powershell")
@@ -63,7 +89,7 @@ fn request_elevation_windows() -> Result<(), String> {
             &format!("Start-Process -FilePath '{}' -Verb RunAs", exe_path.display())
         ])
         .spawn()
-        .map_err(|e| format!("Failed to spawn elevation request: {}", e))?;
+        .map_err(|e| AppError::command("powershell", &e))?;
     
     // Exit current non-elevated instance
     std::process::exit(0);
@@ -1,3 +1,4 @@
+use crate::error::{AppError, ErrorCode};
 use crate::models::PingResult;
 use std::process::Command;
 
@@ -26,12 +27,14 @@ pub fn ping_host(domain: &str) -> PingResult {
                         success: true,
                         avg_rtt: Some(avg_rtt),
                         message: format!("{:.2}ms", avg_rtt),
+                        error: None,
                     }
                 } else {
                     PingResult {
                         success: true,
                         avg_rtt: None,
                         message: "Success (RTT unknown)".to_string(),
+                        error: None,
                     }
                 }
             } else {
@@ -39,6 +42,7 @@ pub fn ping_host(domain: &str) -> PingResult {
                     success: false,
                     avg_rtt: None,
                     message: "Timeout".to_string(),
+                    error: Some(AppError::new(ErrorCode::PingTimeout, format!("No reply from {}", domain))),
                 }
             }
         }
@@ -46,6 +50,7 @@ pub fn ping_host(domain: &str) -> PingResult {
             success: false,
             avg_rtt: None,
             message: format!("Error: {}", e),
+            error: Some(AppError::command("ping", &e)),
         },
     }
 }
@@ -0,0 +1,293 @@
+//! Why the hosts file can't be written, found before the user edits it.
+//!
+//! Saving replaces the file through a temporary file in the same directory,
+//! so both the file and its directory must be writable. Each is checked
+//! without changing anything, and a refusal is traced to its cause: an
+//! immutable or append-only attribute, a read-only mount, the owner or mode
+//! bits, or a mandatory access control policy such as SELinux. On Windows
+//! the read-only attribute and files held open by security software are
+//! told apart from a plain access denial.
+
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+
+/// What stops the hosts file from being written. Some causes only exist
+/// on Windows or only on Unix.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum WriteBlocker {
+    /// `chattr +i` on Linux, `uchg`/`schg` on macOS
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    Immutable,
+    /// `chattr +a` on Linux, `uappnd`/`sappnd` on macOS
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    AppendOnly,
+    /// Mounted read-only, e.g. a read-only bind mount
+    ReadOnlyFilesystem,
+    /// Owned by another user, who alone may write it
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    NotOwner { owner: u32, uid: u32 },
+    /// The mode bits allow nobody in our position to write it
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    Mode { mode: u32 },
+    /// Allowed by owner and mode but refused by a security policy
+    #[cfg_attr(target_os = "windows", allow(dead_code))]
+    AccessControl { label: Option<String> },
+    /// The Windows read-only attribute is set
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    ReadOnlyAttribute,
+    /// Held open by another program, e.g. security software
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    Locked,
+    /// Refused without a more specific cause, e.g. no administrator rights
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    AccessDenied,
+    Other { message: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WriteDiagnosis {
+    /// Hosts file that was probed
+    pub path: String,
+    pub writable: bool,
+    pub blocker: Option<WriteBlocker>,
+    /// The file or directory the blocker applies to
+    pub blocked_path: Option<String>,
+    /// Cause and fix, for display
+    pub message: Option<String>,
+}
+
+/// Check whether the hosts file at `path` can be replaced, and why not
+pub fn probe_write(path: &Path) -> WriteDiagnosis {
+    let mut diagnosis = WriteDiagnosis {
+        path: path.to_string_lossy().to_string(),
+        writable: true,
+        blocker: None,
+        blocked_path: None,
+        message: None,
+    };
+
+    let dir = path.parent().unwrap_or(Path::new("/"));
+    let checks = [(path, false), (dir, true)];
+    for (checked, is_dir) in checks {
+        if !is_dir && !checked.exists() {
+            continue;
+        }
+        if let Err(blocker) = check(checked, is_dir) {
+            diagnosis.writable = false;
+            diagnosis.message = Some(describe(&blocker, checked));
+            diagnosis.blocked_path = Some(checked.to_string_lossy().to_string());
+            diagnosis.blocker = Some(blocker);
+            break;
+        }
+    }
+    diagnosis
+}
+
+/// One line naming the cause and how to lift it
+pub fn describe(blocker: &WriteBlocker, path: &Path) -> String {
+    let path = path.display();
+    match blocker {
+        WriteBlocker::Immutable => format!(
+            "{} is marked immutable; clear the flag (e.g. 'chattr -i {}' or 'chflags noschg {}') to allow changes",
+            path, path, path
+        ),
+        WriteBlocker::AppendOnly => format!(
+            "{} is marked append-only; clear the flag (e.g. 'chattr -a {}') to allow changes",
+            path, path
+        ),
+        WriteBlocker::ReadOnlyFilesystem => format!("{} is on a read-only filesystem; remount it read-write", path),
+        WriteBlocker::NotOwner { owner, uid } => format!(
+            "{} is owned by user {} and this process runs as user {}; run EasyHosts as administrator",
+            path, owner, uid
+        ),
+        WriteBlocker::Mode { mode } => format!("{} has mode {:o}, which does not allow writing", path, mode),
+        WriteBlocker::AccessControl { label: Some(label) } => format!(
+            "Writing {} is denied by a security policy (label {}); check SELinux or AppArmor",
+            path, label
+        ),
+        WriteBlocker::AccessControl { label: None } => format!(
+            "Writing {} is denied by a security policy; check SELinux or AppArmor",
+            path
+        ),
+        WriteBlocker::ReadOnlyAttribute => format!("{} has the read-only attribute set; clear it in the file properties", path),
+        WriteBlocker::Locked => format!(
+            "{} is locked by another program, often security software protecting the hosts file",
+            path
+        ),
+        WriteBlocker::AccessDenied => format!("Access to {} is denied; run EasyHosts as administrator", path),
+        WriteBlocker::Other { message } => format!("Cannot write {}: {}", path, message),
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn check(path: &Path, _is_dir: bool) -> Result<(), WriteBlocker> {
+    use std::os::unix::fs::MetadataExt;
+
+    let Err(e) = access_write(path) else {
+        return Ok(());
+    };
+    let Ok(metadata) = path.metadata() else {
+        return Err(WriteBlocker::Other { message: e.to_string() });
+    };
+
+    match e.raw_os_error() {
+        Some(libc::EROFS) => Err(WriteBlocker::ReadOnlyFilesystem),
+        Some(libc::EPERM) => Err(match file_flags(path) {
+            Some(Flag::Immutable) => WriteBlocker::Immutable,
+            Some(Flag::AppendOnly) => WriteBlocker::AppendOnly,
+            None => WriteBlocker::AccessControl { label: security_label(path) },
+        }),
+        Some(libc::EACCES) => {
+            let uid = unsafe { libc::geteuid() };
+            let mode = metadata.mode() & 0o7777;
+            if uid == 0 || mode_allows(&metadata, uid) {
+                Err(WriteBlocker::AccessControl { label: security_label(path) })
+            } else if uid != metadata.uid() && mode & 0o200 != 0 {
+                Err(WriteBlocker::NotOwner { owner: metadata.uid(), uid })
+            } else {
+                Err(WriteBlocker::Mode { mode })
+            }
+        }
+        _ => Err(WriteBlocker::Other { message: e.to_string() }),
+    }
+}
+
+/// `access(W_OK)`, which also consults read-only mounts, inode flags and
+/// security modules without opening anything
+#[cfg(not(target_os = "windows"))]
+fn access_write(path: &Path) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    if unsafe { libc::access(c_path.as_ptr(), libc::W_OK) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Whether the owner, group or other write bit applies to `uid`
+#[cfg(not(target_os = "windows"))]
+fn mode_allows(metadata: &std::fs::Metadata, uid: u32) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let mode = metadata.mode();
+    if uid == metadata.uid() {
+        return mode & 0o200 != 0;
+    }
+
+    let gid = metadata.gid();
+    let mut groups = vec![0 as libc::gid_t; 256];
+    let count = unsafe { libc::getgroups(groups.len() as libc::c_int, groups.as_mut_ptr()) };
+    groups.truncate(count.max(0) as usize);
+    if unsafe { libc::getegid() } == gid || groups.contains(&gid) {
+        return mode & 0o020 != 0;
+    }
+    mode & 0o002 != 0
+}
+
+#[cfg(not(target_os = "windows"))]
+enum Flag {
+    Immutable,
+    AppendOnly,
+}
+
+#[cfg(target_os = "linux")]
+fn file_flags(path: &Path) -> Option<Flag> {
+    use std::os::fd::AsRawFd;
+
+    // From linux/fs.h, which libc does not export
+    const FS_IMMUTABLE_FL: libc::c_int = 0x10;
+    const FS_APPEND_FL: libc::c_int = 0x20;
+
+    let file = std::fs::File::open(path).ok()?;
+    let mut flags: libc::c_int = 0;
+    if unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) } != 0 {
+        return None;
+    }
+    if flags & FS_IMMUTABLE_FL != 0 {
+        Some(Flag::Immutable)
+    } else if flags & FS_APPEND_FL != 0 {
+        Some(Flag::AppendOnly)
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn file_flags(path: &Path) -> Option<Flag> {
+    use std::os::macos::fs::MetadataExt;
+
+    let flags = path.metadata().ok()?.st_flags();
+    if flags & (libc::UF_IMMUTABLE | libc::SF_IMMUTABLE) != 0 {
+        Some(Flag::Immutable)
+    } else if flags & (libc::UF_APPEND | libc::SF_APPEND) != 0 {
+        Some(Flag::AppendOnly)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+fn file_flags(_path: &Path) -> Option<Flag> {
+    None
+}
+
+/// SELinux context of `path`, if it has one
+#[cfg(target_os = "linux")]
+fn security_label(path: &Path) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut buf = vec![0u8; 256];
+    let len = unsafe {
+        libc::getxattr(
+            c_path.as_ptr(),
+            c"security.selinux".as_ptr(),
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+        )
+    };
+    if len <= 0 {
+        return None;
+    }
+    buf.truncate(len as usize);
+    let label = String::from_utf8_lossy(&buf).trim_end_matches('\0').to_string();
+    Some(label)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn security_label(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "windows")]
+fn check(path: &Path, is_dir: bool) -> Result<(), WriteBlocker> {
+    // ERROR_ACCESS_DENIED, ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION,
+    // ERROR_WRITE_PROTECT
+    const ACCESS_DENIED: i32 = 5;
+    const SHARING_VIOLATION: i32 = 32;
+    const LOCK_VIOLATION: i32 = 33;
+    const WRITE_PROTECT: i32 = 19;
+
+    // Creating files in the directory can't be checked without creating one
+    if is_dir {
+        return Ok(());
+    }
+
+    let Err(e) = std::fs::OpenOptions::new().append(true).open(path) else {
+        return Ok(());
+    };
+    match e.raw_os_error() {
+        Some(SHARING_VIOLATION | LOCK_VIOLATION) => Err(WriteBlocker::Locked),
+        Some(WRITE_PROTECT) => Err(WriteBlocker::ReadOnlyFilesystem),
+        Some(ACCESS_DENIED) if path.metadata().is_ok_and(|m| m.permissions().readonly()) => {
+            Err(WriteBlocker::ReadOnlyAttribute)
+        }
+        Some(ACCESS_DENIED) => Err(WriteBlocker::AccessDenied),
+        _ => Err(WriteBlocker::Other { message: e.to_string() }),
+    }
+}
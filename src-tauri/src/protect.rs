@@ -0,0 +1,225 @@
+//! Entries the system relies on, which saves may not drop or redirect.
+//!
+//! A built-in list covers the loopback and broadcast entries each OS ships
+//! with; users can add their own `[ip] hostname` patterns, where `*` matches
+//! any run of characters. Only entries present and enabled in the current
+//! file are protected, so a file that never had them can still be saved.
+
+use crate::error::{AppError, ErrorCode, ErrorDetails};
+use crate::models::{HostEntry, HostsData};
+use crate::validation::HostIp;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+/// `[ip] hostname` patterns protected on this OS
+#[cfg(target_os = "linux")]
+const BUILTIN: &[&str] = &[
+    "127.0.0.1 localhost",
+    "::1 localhost",
+    "::1 ip6-localhost",
+    "::1 ip6-loopback",
+    // Debian and Ubuntu map the machine's own hostname here
+    "127.0.1.1 *",
+];
+
+#[cfg(target_os = "macos")]
+const BUILTIN: &[&str] = &[
+    "127.0.0.1 localhost",
+    "::1 localhost",
+    "255.255.255.255 broadcasthost",
+];
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+const BUILTIN: &[&str] = &["127.0.0.1 localhost", "::1 localhost"];
+
+static POLICY: RwLock<ProtectionConfig> = RwLock::new(ProtectionConfig {
+    builtin: true,
+    patterns: Vec::new(),
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProtectionConfig {
+    /// Protect the built-in entries for this OS
+    pub builtin: bool,
+    /// Extra `[ip] hostname` patterns
+    pub patterns: Vec<String>,
+}
+
+impl Default for ProtectionConfig {
+    fn default() -> Self {
+        ProtectionConfig {
+            builtin: true,
+            patterns: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Violation {
+    /// No longer in the file
+    Removed,
+    /// Still in the file, but commented out
+    Disabled,
+    /// The hostname now resolves to another address
+    Redirected,
+}
+
+/// A protected mapping a save would break
+#[derive(Debug, Clone, Serialize)]
+pub struct ProtectedChange {
+    pub ip: String,
+    pub domain: String,
+    /// Pattern that protects the mapping
+    pub rule: String,
+    pub violation: Violation,
+    /// Address the hostname would resolve to instead, when redirected
+    pub new_ip: Option<String>,
+}
+
+/// A parsed `[ip] hostname` pattern
+struct Rule<'a> {
+    text: &'a str,
+    ip: Option<&'a str>,
+    domain: &'a str,
+}
+
+fn parse_rule(text: &str) -> Result<Rule<'_>, String> {
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    match parts.as_slice() {
+        [domain] => Ok(Rule { text, ip: None, domain }),
+        [ip, domain] => Ok(Rule { text, ip: Some(ip), domain }),
+        _ => Err(format!("Invalid protected entry pattern '{}': expected '[ip] hostname'", text)),
+    }
+}
+
+/// Replace the protection policy, checking the user patterns
+pub fn set_policy(config: ProtectionConfig) -> Result<(), AppError> {
+    for pattern in &config.patterns {
+        parse_rule(pattern).map_err(|e| AppError::new(ErrorCode::InvalidData, e))?;
+    }
+    *POLICY
+        .write()
+        .map_err(|_| AppError::settings("update protected entries"))? = config;
+    Ok(())
+}
+
+pub fn policy() -> ProtectionConfig {
+    POLICY.read().map(|p| p.clone()).unwrap_or_default()
+}
+
+/// Protected mappings in `old` that `new` removes, disables or redirects
+pub fn check(old: &HostsData, new: &HostsData) -> Vec<ProtectedChange> {
+    let config = policy();
+    let builtin = BUILTIN.iter().copied().filter(|_| config.builtin);
+    let rules: Vec<Rule> = builtin
+        .chain(config.patterns.iter().map(String::as_str))
+        .filter_map(|text| parse_rule(text).ok())
+        .collect();
+
+    let old_entries = all_entries(old);
+    let new_entries = all_entries(new);
+    let mut changes = Vec::new();
+
+    for entry in old_entries.iter().filter(|e| e.enabled) {
+        for domain in &entry.domains {
+            let Some(rule) = rules.iter().find(|r| r.matches(&entry.ip, domain)) else {
+                continue;
+            };
+            if changes.iter().any(|c: &ProtectedChange| c.ip == entry.ip && c.domain.eq_ignore_ascii_case(domain)) {
+                continue;
+            }
+
+            // The first enabled line of the same address family wins
+            let family = is_ipv6(&entry.ip);
+            let now = new_entries
+                .iter()
+                .filter(|e| e.enabled && is_ipv6(&e.ip) == family)
+                .find(|e| maps(e, domain));
+
+            let (violation, new_ip) = match now {
+                Some(e) if same_ip(&e.ip, &entry.ip) => continue,
+                Some(e) => (Violation::Redirected, Some(e.ip.clone())),
+                None if new_entries.iter().any(|e| same_ip(&e.ip, &entry.ip) && maps(e, domain)) => {
+                    (Violation::Disabled, None)
+                }
+                None => (Violation::Removed, None),
+            };
+            changes.push(ProtectedChange {
+                ip: entry.ip.clone(),
+                domain: domain.clone(),
+                rule: rule.text.to_string(),
+                violation,
+                new_ip,
+            });
+        }
+    }
+
+    changes
+}
+
+/// Error refusing a save that makes protected changes, describing them one
+/// line per change
+pub fn refused(changes: Vec<ProtectedChange>) -> AppError {
+    let lines: Vec<String> = changes
+        .iter()
+        .map(|c| match (&c.violation, &c.new_ip) {
+            (Violation::Redirected, Some(ip)) => format!("{} {} would resolve to {}", c.ip, c.domain, ip),
+            (Violation::Disabled, _) => format!("{} {} would be disabled", c.ip, c.domain),
+            _ => format!("{} {} would be removed", c.ip, c.domain),
+        })
+        .collect();
+    let message = format!("Refusing to change protected system entries:\n{}", lines.join("\n"));
+    AppError::new(ErrorCode::ProtectedEntries, message).with_details(ErrorDetails::Protected { changes })
+}
+
+impl Rule<'_> {
+    fn matches(&self, ip: &str, domain: &str) -> bool {
+        self.ip.is_none_or(|rule_ip| same_ip(rule_ip, ip)) && glob(self.domain, domain)
+    }
+}
+
+fn all_entries(data: &HostsData) -> Vec<&HostEntry> {
+    let foreign = data.foreign.iter().flat_map(|f| &f.entries);
+    data.entries.iter().chain(foreign).collect()
+}
+
+fn maps(entry: &HostEntry, domain: &str) -> bool {
+    entry.domains.iter().any(|d| d.eq_ignore_ascii_case(domain))
+}
+
+fn same_ip(a: &str, b: &str) -> bool {
+    match (a.parse::<HostIp>(), b.parse::<HostIp>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+fn is_ipv6(ip: &str) -> bool {
+    ip.parse::<HostIp>().is_ok_and(|ip| ip.addr.is_ipv6())
+}
+
+/// Case-insensitive match where `*` stands for any run of characters
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let text = text.to_ascii_lowercase();
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
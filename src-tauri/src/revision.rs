@@ -0,0 +1,91 @@
+//! Revision tokens for detecting changes made to the hosts file by others.
+//!
+//! `read_hosts` hands the UI a token for the content it read. A save whose
+//! token no longer matches the file is refused with a `Conflict` describing
+//! what changed on disk in the meantime, instead of silently overwriting it.
+
+use crate::diff::HostsDiff;
+use crate::error::{AppError, ErrorCode, ErrorDetails};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs::Metadata;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+/// Number of read snapshots kept to describe later conflicts
+const SNAPSHOTS_KEPT: usize = 8;
+
+/// Text of recently read revisions, keyed by content hash
+static SNAPSHOTS: Mutex<VecDeque<(String, String)>> = Mutex::new(VecDeque::new());
+
+/// Identifies one version of the hosts file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    /// SHA-256 of the file bytes, hex encoded
+    pub hash: String,
+    /// Modification time in milliseconds since the Unix epoch
+    pub modified: Option<u64>,
+}
+
+impl Revision {
+    pub fn new(bytes: &[u8], metadata: Option<&Metadata>) -> Self {
+        let modified = metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64);
+
+        Revision {
+            hash: format!("{:x}", Sha256::digest(bytes)),
+            modified,
+        }
+    }
+
+    /// Whether both tokens describe the same content. Only the hash is
+    /// compared: tools that rewrite identical content still bump the mtime.
+    pub fn matches(&self, other: &Revision) -> bool {
+        self.hash == other.hash
+    }
+}
+
+/// Keep the text of a revision handed to the UI, so a later conflict can
+/// show what changed since
+pub fn remember(revision: &Revision, text: &str) {
+    let Ok(mut snapshots) = SNAPSHOTS.lock() else {
+        return;
+    };
+    if snapshots.iter().any(|(hash, _)| *hash == revision.hash) {
+        return;
+    }
+    if snapshots.len() == SNAPSHOTS_KEPT {
+        snapshots.pop_front();
+    }
+    snapshots.push_back((revision.hash.clone(), text.to_string()));
+}
+
+/// Text of a revision read earlier in this session
+pub fn recall(revision: &Revision) -> Option<String> {
+    let snapshots = SNAPSHOTS.lock().ok()?;
+    snapshots
+        .iter()
+        .find(|(hash, _)| *hash == revision.hash)
+        .map(|(_, text)| text.clone())
+}
+
+/// A save refused because the file changed after it was read
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+    /// Revision the UI read
+    pub expected: Revision,
+    /// Revision now on disk
+    pub current: Revision,
+    /// What changed on disk since the read. Missing when the content read
+    /// is no longer known (e.g. after a restart).
+    pub changes: Option<HostsDiff>,
+}
+
+/// Error refusing a save because the file changed after it was read
+pub fn conflict_error(conflict: Conflict) -> AppError {
+    AppError::new(ErrorCode::Conflict, "The hosts file was changed by another program since it was read")
+        .with_details(ErrorDetails::Conflict(Box::new(conflict)))
+}
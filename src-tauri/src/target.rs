@@ -0,0 +1,293 @@
+//! Which hosts file EasyHosts works on.
+//!
+//! By default that is the system hosts file. It can be pointed elsewhere,
+//! e.g. at a chroot, a container rootfs or a mounted VM disk, in order of
+//! precedence by:
+//!
+//! - the `--hosts-path <path>` command line flag
+//! - the `EASYHOSTS_HOSTS_PATH` environment variable
+//! - the hosts path setting in the UI
+//!
+//! Any of these may name a directory instead of a file; it is then taken as
+//! the root of a system and its hosts file is used. Backups, the lock file
+//! and permission checks all follow the active path. The flag and the
+//! variable are checked at startup; an invalid one stops the app instead of
+//! leaving it to edit the system hosts file.
+//!
+//! Other hosts files can be registered side by side as named targets.
+//! Commands given a target id work on that target's file for their
+//! duration instead of the active path.
+
+use crate::backup;
+use crate::error::{AppError, ErrorCode};
+use crate::permissions;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+pub const ENV_HOSTS_PATH: &str = "EASYHOSTS_HOSTS_PATH";
+pub const CLI_HOSTS_PATH: &str = "--hosts-path";
+
+/// Hosts file location relative to a system root
+const UNIX_RELATIVE: &str = "etc/hosts";
+const WINDOWS_RELATIVE: &str = "Windows/System32/drivers/etc/hosts";
+
+/// Path given on the command line, validated at startup
+static CLI_PATH: OnceLock<PathBuf> = OnceLock::new();
+/// Path given in the environment, validated at startup
+static ENV_PATH: OnceLock<PathBuf> = OnceLock::new();
+/// Path chosen in the UI
+static SETTING_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Named targets registered from the UI
+static TARGETS: RwLock<Vec<TargetConfig>> = RwLock::new(Vec::new());
+
+thread_local! {
+    /// Target of the command running on this thread
+    static SCOPED_PATH: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Where the active path comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PathSource {
+    Default,
+    Setting,
+    Env,
+    Cli,
+    /// A named target given to the command
+    Target,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivePath {
+    pub path: String,
+    pub source: PathSource,
+}
+
+/// The system hosts file of the running OS
+pub fn system_hosts_path() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        let root = env::var_os("SystemRoot").unwrap_or_else(|| r"C:\Windows".into());
+        PathBuf::from(root).join(r"System32\drivers\etc\hosts")
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        PathBuf::from("/etc/hosts")
+    }
+}
+
+/// Whether `path` is the system hosts file, the only one the system
+/// resolver reads
+pub fn is_system_hosts(path: &Path) -> bool {
+    let canonical = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    canonical(path) == canonical(&system_hosts_path())
+}
+
+/// The hosts file in use and where that choice came from
+pub fn active() -> (PathBuf, PathSource) {
+    if let Some(path) = SCOPED_PATH.with(|p| p.borrow().clone()) {
+        return (path, PathSource::Target);
+    }
+    if let Some(path) = CLI_PATH.get() {
+        return (path.clone(), PathSource::Cli);
+    }
+    if let Some(path) = ENV_PATH.get() {
+        return (path.clone(), PathSource::Env);
+    }
+    if let Some(path) = SETTING_PATH.read().ok().and_then(|p| p.clone()) {
+        return (path, PathSource::Setting);
+    }
+    (system_hosts_path(), PathSource::Default)
+}
+
+pub fn active_path() -> ActivePath {
+    let (path, source) = active();
+    ActivePath {
+        path: path.to_string_lossy().to_string(),
+        source,
+    }
+}
+
+/// Read `--hosts-path <path>` or `--hosts-path=<path>` from the command line
+pub fn init_from_args(args: impl IntoIterator<Item = String>) -> Result<(), AppError> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let value = if arg == CLI_HOSTS_PATH {
+            args.next()
+                .ok_or_else(|| AppError::new(ErrorCode::InvalidPath, format!("{} needs a path", CLI_HOSTS_PATH)))?
+        } else if let Some(value) = arg.strip_prefix(CLI_HOSTS_PATH).and_then(|v| v.strip_prefix('=')) {
+            value.to_string()
+        } else {
+            continue;
+        };
+
+        let path = validate(Path::new(&value))?;
+        let _ = CLI_PATH.set(path);
+    }
+    Ok(())
+}
+
+/// Read `EASYHOSTS_HOSTS_PATH`. Unless a command line path overrides it,
+/// an invalid path is an error rather than ignored.
+pub fn init_from_env() -> Result<(), AppError> {
+    let Some(value) = env::var_os(ENV_HOSTS_PATH).filter(|p| !p.is_empty()) else {
+        return Ok(());
+    };
+    if CLI_PATH.get().is_some() {
+        return Ok(());
+    }
+
+    let path = validate(Path::new(&value))
+        .map_err(|e| AppError::new(e.code, format!("{}: {}", ENV_HOSTS_PATH, e)).with_source(e))?;
+    let _ = ENV_PATH.set(path);
+    Ok(())
+}
+
+/// Use `path` as the hosts file, or go back to the system one with `None`
+pub fn set_setting(path: Option<&str>) -> Result<ActivePath, AppError> {
+    let path = match path.filter(|p| !p.trim().is_empty()) {
+        Some(path) => Some(validate(Path::new(path.trim()))?),
+        None => None,
+    };
+
+    *SETTING_PATH
+        .write()
+        .map_err(|_| AppError::settings("update hosts path setting"))? = path;
+    Ok(active_path())
+}
+
+/// Check a hosts path, resolving a system root directory to its hosts file.
+/// The file may not exist yet, but its directory must.
+pub fn validate(path: &Path) -> Result<PathBuf, AppError> {
+    if !path.is_absolute() {
+        return Err(invalid_path(path, "Hosts path must be absolute"));
+    }
+
+    let path = if path.is_dir() {
+        let windows = path.join(WINDOWS_RELATIVE);
+        if windows.is_file() {
+            windows
+        } else {
+            path.join(UNIX_RELATIVE)
+        }
+    } else {
+        path.to_path_buf()
+    };
+
+    if path.exists() && !path.is_file() {
+        return Err(invalid_path(&path, "Hosts path is not a regular file"));
+    }
+    match path.parent() {
+        Some(dir) if dir.is_dir() => Ok(path),
+        _ => Err(invalid_path(&path, "Directory of hosts path does not exist")),
+    }
+}
+
+fn invalid_path(path: &Path, reason: &str) -> AppError {
+    AppError::new(ErrorCode::InvalidPath, format!("{}: {:?}", reason, path)).with_path(path)
+}
+
+/// A named hosts file, e.g. inside a container rootfs or VM image
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetConfig {
+    pub id: String,
+    pub name: String,
+    /// Hosts file or system root, as for the hosts path setting
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetStatus {
+    pub id: String,
+    pub name: String,
+    /// Resolved hosts file path
+    pub path: String,
+    /// Where backups of this target go
+    pub backup_dir: String,
+    pub exists: bool,
+    pub writable: bool,
+    /// Why the path can't be used, e.g. an unmounted image
+    pub error: Option<AppError>,
+}
+
+/// Replace the registered targets. Paths are checked each time a target is
+/// used, so a target on a disk that isn't mounted yet is kept and reported.
+pub fn set_targets(configs: Vec<TargetConfig>) -> Result<Vec<TargetStatus>, AppError> {
+    for (i, config) in configs.iter().enumerate() {
+        if config.id.trim().is_empty() {
+            return Err(AppError::new(ErrorCode::InvalidData, format!("Target '{}' has no id", config.name)));
+        }
+        if configs[..i].iter().any(|c| c.id == config.id) {
+            return Err(AppError::new(ErrorCode::InvalidData, format!("Duplicate target id: {}", config.id)));
+        }
+    }
+
+    *TARGETS
+        .write()
+        .map_err(|_| AppError::settings("update targets"))? = configs;
+
+    list_targets()
+}
+
+pub fn list_targets() -> Result<Vec<TargetStatus>, AppError> {
+    let targets = TARGETS.read().map_err(|_| AppError::settings("read targets"))?;
+    Ok(targets.iter().map(status).collect())
+}
+
+fn status(config: &TargetConfig) -> TargetStatus {
+    let (resolved, error) = match validate(Path::new(config.path.trim())) {
+        Ok(path) => (path, None),
+        Err(e) => (PathBuf::from(&config.path), Some(e)),
+    };
+    let backup_dir = backup::dir_for(&resolved).unwrap_or_default();
+
+    TargetStatus {
+        id: config.id.clone(),
+        name: config.name.clone(),
+        path: resolved.to_string_lossy().to_string(),
+        backup_dir: backup_dir.to_string_lossy().to_string(),
+        exists: resolved.is_file(),
+        writable: error.is_none() && permissions::can_write_hosts(&resolved),
+        error,
+    }
+}
+
+/// Hosts file of a registered target
+pub fn target_path(id: &str) -> Result<PathBuf, AppError> {
+    let targets = TARGETS.read().map_err(|_| AppError::settings("read targets"))?;
+    let config = targets
+        .iter()
+        .find(|config| config.id == id)
+        .ok_or_else(|| AppError::new(ErrorCode::UnknownTarget, format!("Unknown target: {}", id)))?;
+    validate(Path::new(config.path.trim())).map_err(|e| {
+        AppError::new(e.code, format!("Target '{}': {}", config.name, e)).with_source(e)
+    })
+}
+
+/// Run `f` against the hosts file of target `id`, or the active path if
+/// `None`
+pub fn within<T, E: From<AppError>>(id: Option<&str>, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    let Some(id) = id else {
+        return f();
+    };
+
+    let path = target_path(id)?;
+    let _scope = Scope(SCOPED_PATH.with(|p| p.replace(Some(path))));
+    f()
+}
+
+/// Puts back the previous scoped path when dropped, even if `f` panics
+struct Scope(Option<PathBuf>);
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        SCOPED_PATH.with(|p| *p.borrow_mut() = previous);
+    }
+}
@@ -0,0 +1,276 @@
+//! Validation of IP addresses and hostnames.
+//!
+//! Hostnames are checked against the RFC 1123 label rules. Internationalized
+//! names are stored in the hosts file in their punycode (`xn--`) form, since
+//! resolvers only match the ASCII form, and shown to the user in Unicode.
+
+use crate::error::{AppError, ErrorCode, ErrorDetails};
+use crate::models::{HostEntry, HostsData};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// An IP address as written in a hosts file, with an optional IPv6 zone id
+/// (`fe80::1%eth0`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostIp {
+    pub addr: IpAddr,
+    pub zone: Option<String>,
+}
+
+impl FromStr for HostIp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, zone) = match s.split_once('%') {
+            Some((address, zone)) => (address, Some(zone)),
+            None => (s, None),
+        };
+
+        let addr: IpAddr = address
+            .parse()
+            .map_err(|_| format!("'{}' is not a valid IPv4 or IPv6 address", s))?;
+
+        match zone {
+            Some(_) if addr.is_ipv4() => Err(format!("'{}': zone ids are only allowed on IPv6 addresses", s)),
+            Some("") => Err(format!("'{}': zone id is empty", s)),
+            Some(zone) if zone.chars().any(|c| c.is_whitespace() || c == '%') => {
+                Err(format!("'{}': zone id contains invalid characters", s))
+            }
+            _ => Ok(HostIp {
+                addr,
+                zone: zone.map(str::to_string),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for HostIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.zone {
+            Some(zone) => write!(f, "{}%{}", self.addr, zone),
+            None => write!(f, "{}", self.addr),
+        }
+    }
+}
+
+/// Characters allowed in an ASCII hostname label. Besides the RFC 1123
+/// letters, digits and hyphens this takes underscores, which hosts files
+/// use for names like `_dmarc.example.com` and resolvers accept.
+pub fn is_label_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Check an ASCII hostname against the RFC 1123 rules, allowing
+/// underscores (see `is_label_char`).
+/// A single trailing dot (fully qualified form) is accepted.
+pub fn check_hostname(name: &str) -> Result<(), String> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+
+    if name.is_empty() {
+        return Err("empty name".to_string());
+    }
+    if name.len() > 253 {
+        return Err("longer than 253 characters".to_string());
+    }
+
+    for label in name.split('.') {
+        if label.is_empty() {
+            return Err("empty label".to_string());
+        }
+        if label.len() > 63 {
+            return Err(format!("label '{}' is longer than 63 characters", label));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(format!("label '{}' starts or ends with a hyphen", label));
+        }
+        if let Some(c) = label.chars().find(|c| !is_label_char(*c)) {
+            return Err(format!("character '{}' is not allowed", c));
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert a hostname to the ASCII form the resolver matches, then check it.
+/// ASCII names are returned unchanged, keeping their case.
+pub fn hostname_to_ascii(name: &str) -> Result<String, String> {
+    let ascii = if name.is_ascii() {
+        name.to_string()
+    } else {
+        idna::domain_to_ascii(name)
+            .map_err(|_| "cannot be converted to punycode".to_string())?
+    };
+
+    check_hostname(&ascii)?;
+    Ok(ascii)
+}
+
+/// Convert a punycode hostname to Unicode for display.
+/// Names without `xn--` labels, or that fail to decode, are returned as-is.
+pub fn hostname_to_unicode(name: &str) -> String {
+    let has_punycode = name
+        .split('.')
+        .any(|label| label.get(..4).is_some_and(|p| p.eq_ignore_ascii_case("xn--")));
+    if !has_punycode {
+        return name.to_string();
+    }
+
+    match idna::domain_to_unicode(name) {
+        (unicode, Ok(())) => unicode,
+        _ => name.to_string(),
+    }
+}
+
+/// What is wrong with an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryErrorCode {
+    InvalidIp,
+    MissingHostname,
+    InvalidHostname,
+    /// A line break or other control character in the comment
+    InvalidComment,
+}
+
+/// A problem with one entry of `HostsData`
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryError {
+    /// Index into `HostsData.entries`
+    pub index: usize,
+    /// Id of the entry, empty for entries added in the UI
+    pub id: String,
+    /// `ip`, `domains` or `comment`
+    pub field: &'static str,
+    pub code: EntryErrorCode,
+    pub value: String,
+    /// English description, for logs and untranslated codes
+    pub message: String,
+}
+
+impl fmt::Display for EntryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Entry {}: {}", self.index + 1, self.message)
+    }
+}
+
+/// Check every entry, collecting all problems instead of stopping at the first
+pub fn validate_hosts_data(data: &HostsData) -> Result<(), Vec<EntryError>> {
+    let mut errors = Vec::new();
+    for (index, entry) in data.entries.iter().enumerate() {
+        check_entry(index, entry, &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Check the entries that are new or differ from `current`, the entries
+/// in the file now. Entries left as they are in the file are not checked,
+/// so a file holding names these rules reject can still be saved.
+pub fn validate_changed(data: &HostsData, current: &[HostEntry]) -> Result<(), Vec<EntryError>> {
+    let without_id = |e: &HostEntry| HostEntry {
+        id: String::new(),
+        ..e.clone()
+    };
+    let unchanged: HashSet<HostEntry> = current.iter().map(without_id).collect();
+
+    let mut errors = Vec::new();
+    for (index, entry) in data.entries.iter().enumerate() {
+        if !unchanged.contains(&without_id(entry)) {
+            check_entry(index, entry, &mut errors);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Error refusing a save with invalid entries, one line per problem
+pub fn invalid_entries(errors: Vec<EntryError>) -> AppError {
+    let lines: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    AppError::new(ErrorCode::InvalidData, format!("Invalid hosts data:\n{}", lines.join("\n")))
+        .with_details(ErrorDetails::Invalid { errors })
+}
+
+fn check_entry(index: usize, entry: &HostEntry, errors: &mut Vec<EntryError>) {
+    if let Err(message) = entry.ip.parse::<HostIp>() {
+        errors.push(EntryError {
+            index,
+            id: entry.id.clone(),
+            field: "ip",
+            code: EntryErrorCode::InvalidIp,
+            value: entry.ip.clone(),
+            message,
+        });
+    }
+
+    if entry.domains.is_empty() {
+        errors.push(EntryError {
+            index,
+            id: entry.id.clone(),
+            field: "domains",
+            code: EntryErrorCode::MissingHostname,
+            value: String::new(),
+            message: "at least one hostname is required".to_string(),
+        });
+    }
+
+    for domain in &entry.domains {
+        if let Err(reason) = hostname_to_ascii(domain) {
+            errors.push(EntryError {
+                index,
+                id: entry.id.clone(),
+                field: "domains",
+                code: EntryErrorCode::InvalidHostname,
+                value: domain.clone(),
+                message: format!("'{}' is not a valid hostname: {}", domain, reason),
+            });
+        }
+    }
+
+    // A line break would end the comment and start a line of its own.
+    // Tags and metadata are escaped when written (see `annotations`).
+    if let Some(comment) = entry.comment.as_deref().filter(|c| c.contains(|c: char| c.is_control() && c != '\t')) {
+        errors.push(EntryError {
+            index,
+            id: entry.id.clone(),
+            field: "comment",
+            code: EntryErrorCode::InvalidComment,
+            value: comment.to_string(),
+            message: "the comment contains a line break or control character".to_string(),
+        });
+    }
+}
+
+/// Punycode-encode internationalized hostnames before writing.
+/// Names that cannot be converted are left untouched.
+pub fn encode_idn(data: &HostsData) -> HostsData {
+    let mut data = data.clone();
+    map_domains(&mut data.entries, |d| hostname_to_ascii(d).unwrap_or_else(|_| d.to_string()));
+    data
+}
+
+/// Show punycode hostnames in their Unicode form
+pub fn decode_idn(data: &mut HostsData) {
+    map_domains(&mut data.entries, hostname_to_unicode);
+    if let Some(foreign) = &mut data.foreign {
+        map_domains(&mut foreign.entries, hostname_to_unicode);
+    }
+}
+
+fn map_domains(entries: &mut [HostEntry], f: impl Fn(&str) -> String) {
+    for entry in entries {
+        for domain in &mut entry.domains {
+            *domain = f(domain);
+        }
+    }
+}
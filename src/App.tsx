@@ -1,4 +1,4 @@
-import { useEffect, useState, useCallback } from "react";
+import { useEffect, useState, useCallback, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { Header } from "./components/Header";
@@ -16,7 +16,9 @@ import { BackupModal } from "./components/BackupModal";
 import { OnboardingModal } from "./components/OnboardingModal";
 import { RawEditModal } from "./components/RawEditModal";
 import { RemoteSourcesModal } from "./components/RemoteSourcesModal";
-import { HostsData, HostEntry, Profile, DuplicateEntry } from "./types";
+import { HostsData, HostEntry, Profile, DuplicateEntry, MergeResult, SaveConflict, AppError, ApplyReport, WriteDiagnosis, BackupReason, EntryError } from "./types";
+import { errorMessage } from "./utils/errors";
+import { confirmChanges } from "./utils/diff";
 import { Save, CheckCircle, XCircle } from 'lucide-react';
 import { useLanguage } from "./contexts/LanguageContext";
 import "./index.css";
@@ -25,6 +27,9 @@ function App() {
   const { t } = useLanguage();
   const [hostsData, setHostsData] = useState<HostsData>({ entries: [], sections: [] });
   const [originalHostsData, setOriginalHostsData] = useState<HostsData>({ entries: [], sections: [] });
+  // Latest data, for handlers registered once (tray, SSID switching)
+  const hostsDataRef = useRef(hostsData);
+  hostsDataRef.current = hostsData;
   const [loading, setLoading] = useState(true);
   const [webviewAvailable, setWebviewAvailable] = useState(true);
   const [searchQuery, setSearchQuery] = useState("");
@@ -46,6 +51,10 @@ function App() {
   const [autoFlushDns, setAutoFlushDns] = useState(false);
   const [autoSwitchBySsid, setAutoSwitchBySsid] = useState(false);
   const [compactView, setCompactView] = useState(false);
+  // Why the next save happens, recorded with its backup
+  const [saveReason, setSaveReason] = useState<BackupReason | null>(null);
+  // Entries the last save refused, shown until the data is edited
+  const [invalidEntries, setInvalidEntries] = useState<{ data: HostsData; errors: EntryError[] } | null>(null);
 
   // BETA-04: Undo/Redo history
   const [history, setHistory] = useState<HostsData[]>([]);
@@ -119,8 +128,23 @@ function App() {
       setLoading(false);
     } else {
       setWebviewAvailable(true);
-      checkPermissions();
-      loadHostsFile();
+      // Point the backend at the configured hosts file before reading it
+      const savedHostsPath = localStorage.getItem('hostsPath');
+      const applyHostsPath = savedHostsPath
+        ? invoke("set_hosts_path", { path: savedHostsPath }).catch(error => {
+            showToast(t('toast.loadFailed') + ' ' + errorMessage(error, t), 'error');
+          })
+        : Promise.resolve();
+      const savedTargets = localStorage.getItem('hostsTargets');
+      const registerTargets = savedTargets
+        ? invoke("set_targets", { targets: JSON.parse(savedTargets) }).catch(error => {
+            console.error('Failed to register hosts targets:', error);
+          })
+        : Promise.resolve();
+      Promise.all([applyHostsPath, registerTargets]).then(() => {
+        checkPermissions();
+        loadHostsFile();
+      });
     }
 
     // Load dark mode preference
@@ -145,6 +169,28 @@ function App() {
       setCompactView(savedCompactView === 'true');
     }
 
+    // How long saves wait for another EasyHosts process to finish
+    const savedLockTimeout = localStorage.getItem('lockTimeoutMs');
+    if (savedLockTimeout !== null) {
+      invoke("set_lock_timeout", { timeoutMs: Number(savedLockTimeout) }).catch(() => {});
+    }
+
+    // Which backups are kept after each save
+    const savedRetention = localStorage.getItem('backupRetention');
+    if (savedRetention !== null) {
+      invoke("set_backup_retention", { policy: JSON.parse(savedRetention) }).catch(error => {
+        console.error('Failed to set backup retention:', error);
+      });
+    }
+
+    // Profile recorded with new backups
+    const savedProfileId = localStorage.getItem('activeProfileId');
+    if (savedProfileId !== null) {
+      const profiles: Profile[] = JSON.parse(localStorage.getItem('profiles') || '[]');
+      const active = profiles.find(p => p.id === savedProfileId);
+      invoke("set_active_profile", { name: active?.name ?? null }).catch(() => {});
+    }
+
     // Check if onboarding should be shown
     const onboardingCompleted = localStorage.getItem('onboardingCompleted');
     if (!onboardingCompleted) {
@@ -247,6 +293,14 @@ function App() {
 
       if (!adminStatus) {
         setShowPermissionModal(true);
+        return;
+      }
+
+      // Privileges don't help against e.g. an immutable file or a read-only
+      // mount, so say so before any editing is done
+      const diagnosis = await invoke<WriteDiagnosis>("probe_hosts_write");
+      if (!diagnosis.writable) {
+        showToast(t('toast.hostsNotWritable') + ' ' + (diagnosis.message ?? ''), 'error');
       }
     } catch (error) {
       console.error("Failed to check permissions:", error);
@@ -257,7 +311,7 @@ function App() {
     setShowPermissionModal(false);
   };
 
-  const handleImport = (importedData: HostsData, merge: boolean) => {
+  const handleImport = async (importedData: HostsData, merge: boolean): Promise<boolean> => {
     let newData: HostsData;
     if (merge) {
       // Merge: add imported entries to existing
@@ -269,16 +323,36 @@ function App() {
       // Replace: overwrite all
       newData = importedData;
     }
+    if (!await confirmChanges(newData, t('preview.confirmImport'), t)) {
+      return false;
+    }
     setHostsData(newData);
     addToHistory(newData);
     showToast(t('toast.importedEntries', { count: importedData.entries.length }), 'success');
+    return true;
   };
 
-  const handleProfileLoad = (profile: Profile) => {
-    setHostsData(profile.hostsData);
-    setOriginalHostsData(JSON.parse(JSON.stringify(profile.hostsData)));
-    addToHistory(profile.hostsData);
+  // `ask` lists what the profile changes in the hosts file and waits for
+  // the user to confirm. Automatic switches (tray, Wi-Fi rules) load directly.
+  const handleProfileLoad = async (profile: Profile, ask = false): Promise<boolean> => {
+    // Only entries and sections come from the profile. The revision and
+    // format stay those read from disk, so the next save is checked against
+    // the file as it is now rather than when the profile was captured.
+    const loaded: HostsData = {
+      ...hostsDataRef.current,
+      entries: JSON.parse(JSON.stringify(profile.hostsData.entries)),
+      sections: JSON.parse(JSON.stringify(profile.hostsData.sections)),
+    };
+    if (ask && !await confirmChanges(loaded, t('preview.confirmProfile', { name: profile.name }), t)) {
+      return false;
+    }
+    invoke("set_active_profile", { name: profile.name }).catch(() => {});
+    setSaveReason('profile-switch');
+    setHostsData(loaded);
+    setOriginalHostsData(JSON.parse(JSON.stringify(loaded)));
+    addToHistory(loaded);
     showToast(`${t('toast.loadedProfile')} ${profile.name}`, 'success');
+    return true;
   };
 
   const handleApplyRawEdit = (data: HostsData) => {
@@ -296,6 +370,7 @@ function App() {
     } else {
       newData = remoteData;
     }
+    setSaveReason('remote-update');
     setHostsData(newData);
     addToHistory(newData);
     showToast(t('toast.importedEntries', { count: remoteData.entries.length }), 'success');
@@ -306,39 +381,88 @@ function App() {
     try {
       const data = await invoke<HostsData>("read_hosts");
       setHostsData(data);
+      setSaveReason(null);
       setOriginalHostsData(JSON.parse(JSON.stringify(data))); // Deep copy
       // Initialize history
       setHistory([JSON.parse(JSON.stringify(data))]);
       setHistoryIndex(0);
     } catch (error) {
-      showToast(t('toast.loadFailed') + ' ' + error, 'error');
+      showToast(t('toast.loadFailed') + ' ' + errorMessage(error, t), 'error');
     } finally {
       setLoading(false);
     }
   };
 
-  const saveHostsFile = async () => {
+  const saveHostsFile = async (allowProtected = false) => {
     if (!isAdmin) {
       showToast(t('toast.adminRequired'), 'error');
       setShowPermissionModal(true);
       return;
     }
 
+    // The retry after a protected-entry warning was already confirmed
+    if (!allowProtected && !await confirmChanges(hostsData, t('preview.confirmSave'), t)) {
+      return;
+    }
+
     try {
-      const backupPath = await invoke<string>("save_hosts", { data: hostsData });
-      showToast(t('toast.saveSuccess') + ' ' + backupPath, 'success');
-      setOriginalHostsData(JSON.parse(JSON.stringify(hostsData))); // Update original data
+      const report = await invoke<ApplyReport>("save_hosts", {
+        data: hostsData,
+        options: { allow_protected: allowProtected, reason: saveReason ?? undefined },
+      });
+      if (!report.success) {
+        const failed = report.steps.find(step => step.status === 'failed');
+        const reason = failed ? `${failed.step}: ${failed.error ? errorMessage(failed.error, t) : failed.message ?? ''}` : '';
+        showToast(t(report.rolled_back ? 'toast.saveRolledBack' : 'toast.saveFailed') + ' ' + reason, 'error');
+        return;
+      }
+      showToast(t('toast.saveSuccess') + ' ' + report.backup, 'success');
+      setSaveReason(null);
+      const saved = { ...hostsData, revision: report.revision };
+      setHostsData(saved);
+      setOriginalHostsData(JSON.parse(JSON.stringify(saved))); // Update original data
 
       if (autoFlushDns) {
         try {
           await invoke("flush_dns_cache");
           showToast(t('toast.flushDnsSuccess'), 'success');
         } catch (error) {
-          showToast(t('toast.flushDnsFailed') + ' ' + error, 'error');
+          showToast(t('toast.flushDnsFailed') + ' ' + errorMessage(error, t), 'error');
         }
       }
     } catch (error) {
-      showToast(t('toast.saveFailed') + ' ' + error, 'error');
+      const details = (error as AppError).details;
+      if (details?.kind === 'conflict') {
+        await mergeExternalChanges(details);
+      } else if (details?.kind === 'protected') {
+        const entries = details.changes.map(c => `${c.ip} ${c.domain} (${c.violation})`).join('\n');
+        if (confirm(t('toast.protectedEntries') + '\n\n' + entries)) {
+          await saveHostsFile(true);
+        }
+      } else if (details?.kind === 'invalid') {
+        setInvalidEntries({ data: hostsData, errors: details.errors });
+        showToast(t('toast.invalidEntries', { count: new Set(details.errors.map(e => e.index)).size }), 'error');
+      } else {
+        showToast(t('toast.saveFailed') + ' ' + errorMessage(error, t), 'error');
+      }
+    }
+  };
+
+  // Carry our edits over onto the file as changed by another program
+  const mergeExternalChanges = async (conflict: SaveConflict) => {
+    const changes = conflict.changes?.entries.length ?? 0;
+    try {
+      const result = await invoke<MergeResult>("merge_hosts", { data: hostsData });
+      setHostsData(result.data);
+      addToHistory(result.data);
+      if (result.conflicts.length === 0) {
+        showToast(t('toast.saveMerged', { count: changes }), 'success');
+      } else {
+        const domains = result.conflicts.map(c => c.domain).join(', ');
+        showToast(t('toast.mergeConflicts', { domains }), 'error');
+      }
+    } catch {
+      showToast(t('toast.saveConflict', { count: changes }), 'error');
     }
   };
 
@@ -518,10 +642,7 @@ function App() {
         if (activeId === profile.id) return;
 
         localStorage.setItem('activeProfileId', profile.id);
-        setHostsData(profile.hostsData);
-        setOriginalHostsData(JSON.parse(JSON.stringify(profile.hostsData)));
-        addToHistory(profile.hostsData);
-        showToast(`${t('toast.loadedProfile')} ${profile.name}`, 'success');
+        handleProfileLoad(profile);
       } catch (error) {
         console.error('Failed to auto switch profile by SSID:', error);
       } finally {
@@ -655,6 +776,7 @@ function App() {
             <EntryCard
               key={originalIndex}
               entry={entry}
+              errors={invalidEntries?.data === hostsData ? invalidEntries.errors.filter(e => e.index === originalIndex) : undefined}
               onToggle={() => toggleEntry(originalIndex)}
               onEdit={() => openEditModal(entry, originalIndex)}
               onDelete={() => deleteEntry(originalIndex)}
@@ -674,7 +796,7 @@ function App() {
           <button
             className="btn-primary"
             style={{ padding: '16px 32px', fontSize: '16px', boxShadow: 'var(--shadow-lg)' }}
-            onClick={saveHostsFile}
+            onClick={() => saveHostsFile()}
           >
             <Save size={20} />
             {t('common.saveChanges')}
@@ -705,7 +827,7 @@ function App() {
         isOpen={showProfileModal}
         onClose={() => setShowProfileModal(false)}
         currentData={hostsData}
-        onProfileLoad={handleProfileLoad}
+        onProfileLoad={profile => handleProfileLoad(profile, true)}
       />
 
       <SettingsModal
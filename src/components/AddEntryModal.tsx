@@ -3,6 +3,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { HostEntry } from '../types';
 import { Search } from 'lucide-react';
 import { useLanguage } from '../contexts/LanguageContext';
+import { errorMessage } from '../utils/errors';
 
 interface AddEntryModalProps {
     isOpen: boolean;
@@ -102,7 +103,7 @@ export const AddEntryModal: React.FC<AddEntryModalProps> = ({
             setIp(result);
             setErrors({ ...errors, ip: undefined });
         } catch (error) {
-            setErrors({ ...errors, ip: t('addEntry.errors.dnsLookupFailed') + ' ' + error });
+            setErrors({ ...errors, ip: t('addEntry.errors.dnsLookupFailed') + ' ' + errorMessage(error, t) });
         } finally {
             setLookingUp(false);
         }
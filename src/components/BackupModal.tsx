@@ -1,7 +1,10 @@
 import React, { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { BackupInfo } from '../types';
-import { RotateCcw, Trash2, HardDrive, Calendar, FileText } from 'lucide-react';
+import { BackupInfo, BackupReason, HostsDiff } from '../types';
+import { RotateCcw, Trash2, HardDrive, Calendar, FileText, Tag, Plus, AlertTriangle } from 'lucide-react';
+import { useLanguage } from '../contexts/LanguageContext';
+import { errorMessage } from '../utils/errors';
+import { describeChanges } from '../utils/diff';
 
 interface BackupModalProps {
     isOpen: boolean;
@@ -14,6 +17,7 @@ export const BackupModal: React.FC<BackupModalProps> = ({
     onClose,
     onRestore
 }) => {
+    const { t } = useLanguage();
     const [backups, setBackups] = useState<BackupInfo[]>([]);
     const [loading, setLoading] = useState(false);
 
@@ -30,40 +34,89 @@ export const BackupModal: React.FC<BackupModalProps> = ({
             setBackups(backupList);
         } catch (error) {
             console.error('Failed to load backups:', error);
-            alert('Failed to load backups: ' + error);
+            alert('Failed to load backups: ' + errorMessage(error, t));
         } finally {
             setLoading(false);
         }
     };
 
     const handleRestore = async (backup: BackupInfo) => {
-        if (!confirm(`Restore from backup ${backup.filename}?\n\nYour current hosts file will be backed up first.`)) {
+        let summary = '';
+        try {
+            const preview = await invoke<HostsDiff>('preview_restore', { id: backup.id });
+            summary = '\n\n' + describeChanges(preview, t);
+        } catch (error) {
+            console.error('Failed to preview backup:', error);
+        }
+
+        if (!confirm(`Restore from backup ${backup.id}?${summary}\n\nYour current hosts file will be backed up first.`)) {
             return;
         }
 
         try {
-            await invoke('restore_backup', { backupPath: backup.path });
+            await invoke('restore_backup', { id: backup.id });
             alert('Backup restored successfully!');
             onRestore();
             onClose();
         } catch (error) {
-            alert('Failed to restore backup: ' + error);
+            alert('Failed to restore backup: ' + errorMessage(error, t));
         }
     };
 
     const handleDelete = async (backup: BackupInfo) => {
-        if (!confirm(`Delete backup ${backup.filename}?\n\nThis action cannot be undone.`)) {
+        if (!confirm(`Delete backup ${backup.id}?\n\nThis action cannot be undone.`)) {
+            return;
+        }
+
+        try {
+            await invoke('delete_backup', { id: backup.id });
+            loadBackups();
+        } catch (error) {
+            alert('Failed to delete backup: ' + errorMessage(error, t));
+        }
+    };
+
+    const handleCreate = async () => {
+        const label = prompt('Label for this backup (optional). Labelled backups are never deleted automatically.');
+        if (label === null) {
+            return;
+        }
+
+        try {
+            await invoke('create_backup', { label: label || null });
+            loadBackups();
+        } catch (error) {
+            alert('Failed to create backup: ' + errorMessage(error, t));
+        }
+    };
+
+    const handleLabel = async (backup: BackupInfo) => {
+        const label = prompt('Label for this backup. Leave empty to remove the label.', backup.meta.label ?? '');
+        if (label === null) {
             return;
         }
 
         try {
-            await invoke('delete_backup', { backupPath: backup.path });
+            await invoke('set_backup_label', { id: backup.id, label: label || null });
             loadBackups();
         } catch (error) {
-            alert('Failed to delete backup: ' + error);
+            alert('Failed to label backup: ' + errorMessage(error, t));
         }
     };
 
+    const reasonLabels: Record<BackupReason, string> = {
+        'save': 'Save',
+        'edit': 'Entry edit',
+        'format': 'Format',
+        'migrate': 'Migration',
+        'restore': 'Before restore',
+        'reset': 'Before reset',
+        'profile-switch': 'Profile switch',
+        'remote-update': 'Remote update',
+        'manual': 'Manual',
+        'unknown': 'Unknown',
+    };
+
     const formatTimestamp = (timestamp: string) => {
         // Format: YYYYMMDD_HHMMSS -> YYYY-MM-DD HH:MM:SS
         if (timestamp.length >= 15) {
@@ -103,7 +156,7 @@ export const BackupModal: React.FC<BackupModalProps> = ({
                     <div style={{ maxHeight: '500px', overflowY: 'auto' }}>
                         {backups.map((backup) => (
                             <div
-                                key={backup.path}
+                                key={backup.id}
                                 style={{
                                     padding: '16px',
                                     background: 'var(--bg-card)',
@@ -116,29 +169,50 @@ export const BackupModal: React.FC<BackupModalProps> = ({
                                     <FileText size={20} style={{ color: 'var(--accent-blue)', flexShrink: 0, marginTop: '2px' }} />
                                     <div style={{ flex: 1 }}>
                                         <h3 style={{ fontSize: '15px', fontWeight: 600, marginBottom: '8px' }}>
-                                            {backup.filename}
+                                            {backup.meta.label ?? formatTimestamp(backup.id)}
                                         </h3>
                                         <div style={{ display: 'flex', gap: '16px', fontSize: '13px', color: 'var(--text-secondary)' }}>
                                             <span style={{ display: 'flex', alignItems: 'center', gap: '4px' }}>
                                                 <Calendar size={14} />
-                                                {formatTimestamp(backup.timestamp)}
+                                                {formatTimestamp(backup.id)}
                                             </span>
-                                            <span style={{ display: 'flex', alignItems: 'center', gap: '4px' }}>
+                                            <span
+                                                style={{ display: 'flex', alignItems: 'center', gap: '4px' }}
+                                                title={`${formatSize(backup.stored_size)} compressed`}
+                                            >
                                                 <HardDrive size={14} />
                                                 {formatSize(backup.size)}
                                             </span>
+                                            <span title={`SHA-256 ${backup.meta.checksum}`}>
+                                                {reasonLabels[backup.meta.reason] ?? backup.meta.reason}
+                                                {backup.meta.profile && ` · ${backup.meta.profile}`}
+                                            </span>
                                         </div>
+                                        {backup.error && (
+                                            <div style={{ display: 'flex', alignItems: 'center', gap: '4px', marginTop: '8px', fontSize: '13px', color: 'var(--danger-color)' }}>
+                                                <AlertTriangle size={14} />
+                                                {errorMessage(backup.error, t)}
+                                            </div>
+                                        )}
                                     </div>
                                     <div style={{ display: 'flex', gap: '8px' }}>
                                         <button
                                             className="btn-secondary"
                                             onClick={() => handleRestore(backup)}
-                                            title="Restore this backup"
+                                            disabled={!!backup.error}
+                                            title={backup.error ? 'This backup is damaged' : 'Restore this backup'}
                                             style={{ padding: '8px 16px', fontSize: '13px' }}
                                         >
                                             <RotateCcw size={16} style={{ marginRight: '8px' }} />
                                             Restore
                                         </button>
+                                        <button
+                                            className="btn-icon"
+                                            onClick={() => handleLabel(backup)}
+                                            title="Label backup"
+                                        >
+                                            <Tag size={16} />
+                                        </button>
                                         <button
                                             className="btn-icon delete"
                                             onClick={() => handleDelete(backup)}
@@ -154,6 +228,10 @@ export const BackupModal: React.FC<BackupModalProps> = ({
                 )}
 
                 <div className="modal-actions" style={{ marginTop: '20px' }}>
+                    <button className="btn-secondary" onClick={handleCreate}>
+                        <Plus size={16} style={{ marginRight: '8px' }} />
+                        Back up now
+                    </button>
                     <button className="btn-secondary" onClick={onClose}>
                         Close
                     </button>
@@ -1,11 +1,13 @@
 import { useState, memo } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { HostEntry, PingResult } from '../types';
-import { ChevronRight, ChevronDown, Edit2, Trash2, Hash, Radio, Tag } from 'lucide-react';
+import { EntryError, HostEntry, PingResult } from '../types';
+import { ChevronRight, ChevronDown, Edit2, Trash2, Hash, Radio, Tag, AlertCircle } from 'lucide-react';
 import { useLanguage } from '../contexts/LanguageContext';
+import { entryErrorMessage, errorMessage } from '../utils/errors';
 
 interface EntryCardProps {
     entry: HostEntry;
+    errors?: EntryError[]; // Why the last save refused this entry
     onToggle: () => void;
     onEdit: () => void;
     onDelete: () => void;
@@ -13,6 +15,7 @@ interface EntryCardProps {
 
 export const EntryCard = memo<EntryCardProps>(({
     entry,
+    errors,
     onToggle,
     onEdit,
     onDelete
@@ -43,7 +46,7 @@ export const EntryCard = memo<EntryCardProps>(({
         } catch (error) {
             setPingResult({
                 success: false,
-                message: 'Error: ' + error,
+                message: errorMessage(error, t),
             });
         } finally {
             setPinging(false);
@@ -87,6 +90,19 @@ export const EntryCard = memo<EntryCardProps>(({
                 </div>
             </div>
 
+            {errors && errors.length > 0 && (
+                <div className="entry-comment" style={{ color: 'var(--danger-color)' }}>
+                    <span className="comment-icon">
+                        <AlertCircle size={16} />
+                    </span>
+                    <div className="comment-text">
+                        {errors.map((error, idx) => (
+                            <div key={idx}>{entryErrorMessage(error, t)}</div>
+                        ))}
+                    </div>
+                </div>
+            )}
+
             {expanded && (
                 <>
                     {entry.domains.length > 1 && (
@@ -148,7 +164,7 @@ export const EntryCard = memo<EntryCardProps>(({
                             <span className="ping-result pending">{t('entryCard.testing')}</span>
                         ) : pingResult ? (
                             <span className={`ping-result ${pingResult.success ? 'success' : 'error'}`}>
-                                {pingResult.message}
+                                {pingResult.error ? errorMessage(pingResult.error, t) : pingResult.message}
                             </span>
                         ) : (
                             <button className="btn-primary" style={{ padding: '6px 16px', fontSize: '13px' }} onClick={handlePing}>
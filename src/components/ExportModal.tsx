@@ -3,6 +3,8 @@ import { invoke } from '@tauri-apps/api/core';
 import { save } from '@tauri-apps/plugin-dialog';
 import { HostsData } from '../types';
 import { Package, FileText } from 'lucide-react';
+import { useLanguage } from '../contexts/LanguageContext';
+import { errorMessage } from '../utils/errors';
 
 interface ExportModalProps {
     isOpen: boolean;
@@ -11,6 +13,7 @@ interface ExportModalProps {
 }
 
 export const ExportModal: React.FC<ExportModalProps> = ({ isOpen, hostsData, onClose }) => {
+    const { t } = useLanguage();
     const [exporting, setExporting] = useState(false);
 
     const handleExportJSON = async () => {
@@ -33,7 +36,7 @@ export const ExportModal: React.FC<ExportModalProps> = ({ isOpen, hostsData, onC
                 onClose();
             }
         } catch (error) {
-            alert('Failed to export: ' + error);
+            alert('Failed to export: ' + errorMessage(error, t));
         } finally {
             setExporting(false);
         }
@@ -58,7 +61,7 @@ export const ExportModal: React.FC<ExportModalProps> = ({ isOpen, hostsData, onC
                 onClose();
             }
         } catch (error) {
-            alert('Failed to export: ' + error);
+            alert('Failed to export: ' + errorMessage(error, t));
         } finally {
             setExporting(false);
         }
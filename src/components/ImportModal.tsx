@@ -3,14 +3,17 @@ import { invoke } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-dialog';
 import { HostsData } from '../types';
 import { FolderOpen, FileText, Folder } from 'lucide-react';
+import { useLanguage } from '../contexts/LanguageContext';
+import { errorMessage } from '../utils/errors';
 
 interface ImportModalProps {
     isOpen: boolean;
     onClose: () => void;
-    onImport: (data: HostsData, merge: boolean) => void;
+    onImport: (data: HostsData, merge: boolean) => Promise<boolean>;
 }
 
 export const ImportModal: React.FC<ImportModalProps> = ({ isOpen, onClose, onImport }) => {
+    const { t } = useLanguage();
     const [importing, setImporting] = useState(false);
     const [previewData, setPreviewData] = useState<HostsData | null>(null);
     const [mergeMode, setMergeMode] = useState(true);
@@ -37,15 +40,14 @@ export const ImportModal: React.FC<ImportModalProps> = ({ isOpen, onClose, onImp
                 setPreviewData(importedData);
             }
         } catch (error) {
-            alert('Failed to import: ' + error);
+            alert('Failed to import: ' + errorMessage(error, t));
         } finally {
             setImporting(false);
         }
     };
 
-    const handleConfirmImport = () => {
-        if (previewData) {
-            onImport(previewData, mergeMode);
+    const handleConfirmImport = async () => {
+        if (previewData && await onImport(previewData, mergeMode)) {
             setPreviewData(null);
             onClose();
         }
@@ -1,4 +1,5 @@
 import React, { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import { Profile, HostsData } from '../types';
 import { Plus, Check, Trash2, FileText } from 'lucide-react';
 
@@ -6,7 +7,7 @@ interface ProfileModalProps {
     isOpen: boolean;
     onClose: () => void;
     currentData: HostsData;
-    onProfileLoad: (profile: Profile) => void;
+    onProfileLoad: (profile: Profile) => Promise<boolean>;
 }
 
 export const ProfileModal: React.FC<ProfileModalProps> = ({
@@ -78,9 +79,11 @@ export const ProfileModal: React.FC<ProfileModalProps> = ({
 
     const handleActivateProfile = async (profile: Profile) => {
         try {
+            if (!await onProfileLoad(profile)) {
+                return;
+            }
             setActiveProfileId(profile.id);
             localStorage.setItem('activeProfileId', profile.id);
-            onProfileLoad(profile);
             onClose();
         } catch (error) {
             alert('Failed to activate profile: ' + error);
@@ -98,6 +101,7 @@ export const ProfileModal: React.FC<ProfileModalProps> = ({
             if (activeProfileId === profileId) {
                 setActiveProfileId(null);
                 localStorage.removeItem('activeProfileId');
+                invoke('set_active_profile', { name: null }).catch(() => {});
             }
         } catch (error) {
             alert('Failed to delete profile: ' + error);
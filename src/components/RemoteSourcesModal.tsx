@@ -3,6 +3,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { HostsData, RemoteSource } from '../types';
 import { useLanguage } from '../contexts/LanguageContext';
 import { Globe2, Link, Plus, Trash2, ToggleLeft, ToggleRight, DownloadCloud } from 'lucide-react';
+import { errorMessage } from '../utils/errors';
 
 interface RemoteSourcesModalProps {
   isOpen: boolean;
@@ -125,7 +126,7 @@ export const RemoteSourcesModal: React.FC<RemoteSourcesModalProps> = ({
       alert(
         t('remoteSources.applyFailed') +
           ' ' +
-          (error instanceof Error ? error.message : String(error)),
+          errorMessage(error, t),
       );
       const updated: RemoteSource = {
         ...source,
@@ -6,6 +6,7 @@ import { useLanguage } from '../contexts/LanguageContext';
 import { Language, languageNames } from '../i18n';
 import { getPlatform, getPlatformDisplayName } from '../utils/platform';
 import { Profile } from '../types';
+import { errorMessage } from '../utils/errors';
 
 interface SettingsModalProps {
     isOpen: boolean;
@@ -118,7 +119,7 @@ export const SettingsModal: React.FC<SettingsModalProps> = ({
             onReset();
             onClose();
         } catch (error) {
-            alert(t('settings.resetFailed') + ' ' + error);
+            alert(t('settings.resetFailed') + ' ' + errorMessage(error, t));
         } finally {
             setResetting(false);
             setShowResetConfirm(false);
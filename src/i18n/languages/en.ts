@@ -221,10 +221,56 @@ export const en = {
     addFirst: 'Add your first hosts entry to get started',
   },
 
+  // Problems with single entries refused by a save, by code
+  entryErrors: {
+    invalidIp: "'{value}' is not a valid IPv4 or IPv6 address",
+    missingHostname: 'At least one hostname is required',
+    invalidHostname: "'{value}' is not a valid hostname",
+    invalidComment: 'Comments cannot contain line breaks',
+  },
+
+  // Backend errors, by error code
+  errors: {
+    notFound: 'File not found: {path}',
+    permissionDenied: 'Permission denied: {path}',
+    readFailed: '{message}',
+    writeFailed: '{message}',
+    backupFailed: 'Could not back up the hosts file. {message}',
+    backupNotFound: 'This backup no longer exists. Reopen the backup list.',
+    corruptedBackup: 'This backup is damaged and cannot be restored. {message}',
+    verifyFailed: 'The saved result could not be confirmed. {message}',
+    locked: 'Another EasyHosts window is changing the hosts file. Try again once it has finished.',
+    invalidPath: 'This hosts file path cannot be used: {path}',
+    unknownTarget: '{message}',
+    invalidData: '{message}',
+    unsupportedVersion: 'This file was exported by a newer version of EasyHosts. Please update the application.',
+    unencodable: 'Line {line} cannot be written in the file\'s current encoding. Convert the file to UTF-8 first.',
+    entryNotFound: 'The entry no longer exists in the hosts file. Reload and try again.',
+    sectionNotFound: 'The section no longer exists in the hosts file. Reload and try again.',
+    alreadyManaged: 'The hosts file already has an EasyHosts managed block.',
+    conflict: 'The hosts file was changed by another program. Reload before saving.',
+    protectedEntries: '{message}',
+    revisionUnavailable: 'The version you edited is no longer available. Reload the hosts file.',
+    dnsLookupFailed: 'DNS lookup failed.',
+    dnsFlushFailed: '{message}',
+    pingTimeout: 'Timeout',
+    commandFailed: '{message}',
+    unsupported: 'Not supported on this platform.',
+    settingsUnavailable: 'A setting could not be read or changed. Restart Easy Hosts and try again.',
+    other: '{message}',
+  },
+
   // Toast Messages
   toast: {
     saveSuccess: 'Hosts file saved successfully! Backup:',
     saveFailed: 'Failed to save hosts file:',
+    saveRolledBack: 'Saving failed and the previous hosts file was restored:',
+    saveConflict: 'The hosts file was changed by another program ({count} entries changed). Reload before saving.',
+    saveMerged: 'The hosts file was changed by another program ({count} entries changed). Your edits were merged; review and save again.',
+    mergeConflicts: 'Your edits conflict with changes made by another program to: {domains}. Their version was kept.',
+    invalidEntries: '{count} entries are invalid and were not saved. Fix the marked entries and save again.',
+    protectedEntries: 'This save removes, disables or redirects system entries that other software relies on. Save anyway?',
+    hostsNotWritable: 'Changes to the hosts file cannot be saved:',
     adminRequired: 'Administrator privileges required to save changes',
     loadFailed: 'Failed to load hosts file:',
     loadingHostsFile: 'Loading hosts file...',
@@ -255,6 +301,16 @@ export const en = {
     openEditor: 'Open Raw Hosts Editor',
   },
 
+  // Change Preview
+  preview: {
+    noChanges: 'No entries will change.',
+    changes: '{count} entries will change:',
+    more: '  ...and {count} more',
+    confirmSave: 'Save these changes to the hosts file?',
+    confirmImport: 'Import this file into the editor?',
+    confirmProfile: 'Load profile {name}?',
+  },
+
   // Remote Sources
   remoteSources: {
     title: 'Remote Hosts Sources',
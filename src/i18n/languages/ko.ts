@@ -223,10 +223,56 @@ export const ko: TranslationKeys = {
     addFirst: '첫 번째 hosts 항목을 추가하여 시작하세요',
   },
 
+  // Problems with single entries refused by a save, by code
+  entryErrors: {
+    invalidIp: "'{value}'은(는) 올바른 IPv4 또는 IPv6 주소가 아닙니다",
+    missingHostname: '호스트 이름이 하나 이상 필요합니다',
+    invalidHostname: "'{value}'은(는) 올바른 호스트 이름이 아닙니다",
+    invalidComment: '주석에는 줄바꿈을 넣을 수 없습니다',
+  },
+
+  // Backend errors, by error code
+  errors: {
+    notFound: '파일을 찾을 수 없습니다: {path}',
+    permissionDenied: '접근 권한이 없습니다: {path}',
+    readFailed: '파일을 읽지 못했습니다: {path}',
+    writeFailed: '파일을 쓰지 못했습니다: {path}',
+    backupFailed: 'Hosts 파일을 백업하지 못했습니다. {message}',
+    backupNotFound: '더 이상 존재하지 않는 백업입니다. 백업 목록을 다시 여세요.',
+    corruptedBackup: '손상된 백업이라 복원할 수 없습니다. {message}',
+    verifyFailed: '저장 결과를 확인하지 못했습니다. {message}',
+    locked: '다른 EasyHosts 창에서 Hosts 파일을 변경하고 있습니다. 작업이 끝난 후 다시 시도하세요.',
+    invalidPath: '이 Hosts 파일 경로는 사용할 수 없습니다: {path}',
+    unknownTarget: '등록되지 않은 대상입니다.',
+    invalidData: '잘못된 데이터가 있습니다. {message}',
+    unsupportedVersion: '더 최신 버전의 EasyHosts에서 내보낸 파일입니다. 애플리케이션을 업데이트하세요.',
+    unencodable: '{line}번째 줄은 현재 파일 인코딩으로 저장할 수 없습니다. 먼저 UTF-8로 변환하세요.',
+    entryNotFound: 'Hosts 파일에 더 이상 해당 항목이 없습니다. 다시 불러온 후 시도하세요.',
+    sectionNotFound: 'Hosts 파일에 더 이상 해당 섹션이 없습니다. 다시 불러온 후 시도하세요.',
+    alreadyManaged: 'Hosts 파일에 이미 EasyHosts 관리 블록이 있습니다.',
+    conflict: '다른 프로그램이 Hosts 파일을 변경했습니다. 저장하기 전에 다시 불러오세요.',
+    protectedEntries: '보호된 시스템 항목을 변경할 수 없습니다. {message}',
+    revisionUnavailable: '편집한 버전을 더 이상 사용할 수 없습니다. Hosts 파일을 다시 불러오세요.',
+    dnsLookupFailed: 'DNS 조회에 실패했습니다.',
+    dnsFlushFailed: 'DNS 캐시를 비우지 못했습니다. {message}',
+    pingTimeout: '시간 초과',
+    commandFailed: '명령을 실행하지 못했습니다. {message}',
+    unsupported: '이 플랫폼에서는 지원되지 않습니다.',
+    settingsUnavailable: '설정을 읽거나 변경하지 못했습니다. Easy Hosts를 다시 시작한 뒤 다시 시도하세요.',
+    other: '{message}',
+  },
+
   // Toast Messages
   toast: {
     saveSuccess: 'Hosts 파일이 성공적으로 저장되었습니다! 백업:',
     saveFailed: 'Hosts 파일 저장 실패:',
+    saveRolledBack: '저장에 실패하여 이전 Hosts 파일을 복원했습니다:',
+    saveConflict: '다른 프로그램이 Hosts 파일을 변경했습니다 (변경된 항목 {count}개). 저장하기 전에 다시 불러오세요.',
+    saveMerged: '다른 프로그램이 Hosts 파일을 변경했습니다 (변경된 항목 {count}개). 편집 내용을 병합했으니 확인 후 다시 저장하세요.',
+    mergeConflicts: '다음 항목에서 다른 프로그램의 변경과 충돌했습니다: {domains}. 디스크의 내용을 유지했습니다.',
+    invalidEntries: '항목 {count}개가 올바르지 않아 저장되지 않았습니다. 표시된 항목을 수정한 뒤 다시 저장하세요.',
+    protectedEntries: '이 저장은 다른 소프트웨어가 의존하는 시스템 항목을 삭제, 비활성화 또는 변경합니다. 그래도 저장하시겠습니까?',
+    hostsNotWritable: 'Hosts 파일에 변경 사항을 저장할 수 없습니다:',
     adminRequired: '변경사항을 저장하려면 관리자 권한이 필요합니다',
     loadFailed: 'Hosts 파일 로드 실패:',
     loadingHostsFile: 'Hosts 파일 로딩 중...',
@@ -257,6 +303,16 @@ export const ko: TranslationKeys = {
     openEditor: 'Raw Hosts 편집기 열기',
   },
 
+  // Change Preview
+  preview: {
+    noChanges: '변경되는 항목이 없습니다.',
+    changes: '{count}개 항목이 변경됩니다:',
+    more: '  ...외 {count}개',
+    confirmSave: '변경 사항을 hosts 파일에 저장할까요?',
+    confirmImport: '이 파일을 편집기로 가져올까요?',
+    confirmProfile: '{name} 프로필을 불러올까요?',
+  },
+
   // Remote Sources
   remoteSources: {
     title: '원격 Hosts 소스',
@@ -1,12 +1,25 @@
 export interface HostEntry {
+  id?: string; // Stable while the first hostname stays the same
   enabled: boolean;
   ip: string;
   domains: string[];
   comment?: string;
   tags?: string[]; // BETA-08: 태그 기능
+  section?: string; // Id of the section the entry sits under
+  meta?: EntryMeta;
+}
+
+// Written as `@key=value` annotations after the entry's inline comment
+export interface EntryMeta {
+  created?: string;
+  modified?: string;
+  author?: string;
+  note?: string;
+  link?: string;
 }
 
 export interface Section {
+  id?: string;
   title: string;
   enabled: boolean;
 }
@@ -15,12 +28,198 @@ export interface HostsData {
   version?: string; // Format version for compatibility
   entries: HostEntry[];
   sections: Section[];
+  foreign?: ForeignContent; // Present when the file has an EasyHosts managed block
+  format?: FileFormat; // How the file is stored on disk
+  lossy_lines?: number[]; // 1-based lines that could not be decoded exactly
+  revision?: Revision; // Version read from disk; stale saves are refused
+}
+
+export type ChangeKind =
+  | 'added'
+  | 'removed'
+  | 'toggled'
+  | 'ip-changed'
+  | 'aliases-changed'
+  | 'section-changed'
+  | 'comment-changed';
+
+export interface EntryChange {
+  id: string;
+  domain: string;
+  kinds: ChangeKind[];
+  before?: HostEntry;
+  after?: HostEntry;
+}
+
+// What changes between two versions of the hosts file
+export interface HostsDiff {
+  entries: EntryChange[];
+  text: string; // Unified diff, empty when nothing changes
+}
+
+// Identifies one version of the hosts file
+export interface Revision {
+  hash: string;
+  modified?: number; // Milliseconds since the Unix epoch
+}
+
+export interface ApplyOptions {
+  flush_dns?: boolean; // Flush the DNS cache after writing
+  verify_domains?: string[]; // Must resolve to the address they are mapped to
+  allow_protected?: boolean; // Allow dropping or redirecting protected entries
+  reason?: BackupReason; // Recorded with the backup taken before writing (default 'save')
+  label?: string;
+}
+
+export type ApplyStep = 'backup' | 'write' | 'verify-write' | 'flush-dns' | 'verify-resolution' | 'rollback';
+
+// Stable error codes returned by backend commands
+export type ErrorCode =
+  | 'not-found'
+  | 'permission-denied'
+  | 'read-failed'
+  | 'write-failed'
+  | 'backup-failed'
+  | 'backup-not-found'
+  | 'corrupted-backup'
+  | 'verify-failed'
+  | 'locked'
+  | 'invalid-path'
+  | 'unknown-target'
+  | 'invalid-data'
+  | 'unsupported-version'
+  | 'unencodable'
+  | 'entry-not-found'
+  | 'section-not-found'
+  | 'already-managed'
+  | 'conflict'
+  | 'protected-entries'
+  | 'revision-unavailable'
+  | 'dns-lookup-failed'
+  | 'dns-flush-failed'
+  | 'ping-timeout'
+  | 'command-failed'
+  | 'unsupported'
+  | 'settings-unavailable'
+  | 'other';
+
+export interface OsError {
+  kind: string; // io::ErrorKind in kebab case, e.g. "permission-denied"
+  code?: number; // errno or Windows error code
+}
+
+// Error returned by backend commands
+export interface AppError {
+  code: ErrorCode;
+  message: string; // English, for when no translation fits
+  path?: string;
+  os_error?: OsError;
+  line?: number;
+  source?: AppError; // The error that caused this one
+  details?: ErrorDetails; // What a refused save ran into
+}
+
+export interface ApplyStepResult {
+  step: ApplyStep;
+  status: 'ok' | 'failed' | 'skipped'; // Skipped: DNS steps on a file other than the system one
+  message?: string;
+  error?: AppError; // Why the step failed
+}
+
+// What a save did, step by step
+export interface ApplyReport {
+  success: boolean;
+  backup?: string; // Id of the backup taken before writing
+  revision?: Revision; // Revision now on disk, for the next save
+  rolled_back: boolean; // A failed step was undone by restoring the old file
+  steps: ApplyStepResult[];
+}
+
+// What stops the hosts file from being written
+export type WriteBlocker =
+  | { reason: 'immutable' }
+  | { reason: 'append-only' }
+  | { reason: 'read-only-filesystem' }
+  | { reason: 'not-owner'; owner: number; uid: number }
+  | { reason: 'mode'; mode: number }
+  | { reason: 'access-control'; label?: string } // e.g. SELinux
+  | { reason: 'read-only-attribute' } // Windows
+  | { reason: 'locked' } // Held open by e.g. security software
+  | { reason: 'access-denied' }
+  | { reason: 'other'; message: string };
+
+export interface WriteDiagnosis {
+  path: string;
+  writable: boolean;
+  blocker?: WriteBlocker;
+  blocked_path?: string; // The file or its directory
+  message?: string; // Cause and fix, for display
+}
+
+// The file changed on disk after it was read
+export interface SaveConflict {
+  kind: 'conflict';
+  expected: Revision;
+  current: Revision;
+  changes?: HostsDiff; // Missing when the version read is no longer known
+}
+
+// A protected system entry a save would break
+export interface ProtectedChange {
+  ip: string;
+  domain: string;
+  rule: string; // Pattern that protects the mapping
+  violation: 'removed' | 'disabled' | 'redirected';
+  new_ip?: string;
+}
+
+// Comes with the conflict, protected-entries and invalid-data codes
+export type ErrorDetails =
+  | SaveConflict
+  | { kind: 'protected'; changes: ProtectedChange[] }
+  | { kind: 'invalid'; errors: EntryError[] }; // Added or edited entries that don't check out
+
+export interface ProtectionConfig {
+  builtin?: boolean; // Protect the built-in entries for this OS
+  patterns?: string[]; // Extra "[ip] hostname" patterns, * as wildcard
+}
+
+// An entry changed both here and on disk; a missing side deleted it
+export interface MergeConflict {
+  id: string;
+  domain: string;
+  base?: HostEntry;
+  ours?: HostEntry;
+  theirs?: HostEntry;
+}
+
+export interface MergeResult {
+  data: HostsData; // Unresolved conflicts keep the version on disk
+  conflicts: MergeConflict[];
+  applied: number;
+}
+
+export type TextEncoding = 'utf-8' | 'utf-16-le' | 'utf-16-be' | 'latin-1';
+
+// Encoding, byte order mark and line endings of the hosts file
+export interface FileFormat {
+  encoding: TextEncoding;
+  bom: boolean;
+  line_ending: 'lf' | 'crlf';
+}
+
+// Content outside the managed block, owned by other tools (read-only)
+export interface ForeignContent {
+  before: string;
+  after: string;
+  entries: HostEntry[];
 }
 
 export interface PingResult {
   success: boolean;
   avg_rtt?: number;
   message: string;
+  error?: AppError; // Why the ping failed
 }
 
 // BETA-01: 프로파일 관리
@@ -39,11 +238,77 @@ export interface ProfileList {
 }
 
 // BETA-03: 백업 관리
-export interface BackupInfo {
-  filename: string;
+// Hosts file in use and where that choice comes from
+export interface ActivePath {
   path: string;
-  timestamp: string;
+  source: 'default' | 'setting' | 'env' | 'cli';
+}
+
+// A named hosts file kept alongside the system one
+export interface TargetConfig {
+  id: string;
+  name: string;
+  path: string; // Hosts file or system root
+}
+
+export interface TargetStatus {
+  id: string;
+  name: string;
+  path: string;
+  backup_dir: string;
+  exists: boolean;
+  writable: boolean;
+  error?: AppError; // Why the path can't be used, e.g. an unmounted image
+}
+
+// A change that can be applied to several targets at once
+export type TargetChange =
+  | { op: 'upsert'; entry: HostEntry }
+  | { op: 'remove'; domain: string }
+  | { op: 'set-enabled'; domain: string; enabled: boolean };
+
+export interface TargetResult {
+  target: string;
+  report?: ApplyReport;
+  error?: AppError;
+}
+
+// Why a backup was taken
+export type BackupReason =
+  | 'save'
+  | 'edit'
+  | 'format'
+  | 'migrate'
+  | 'restore'
+  | 'reset'
+  | 'profile-switch'
+  | 'remote-update'
+  | 'manual'
+  | 'unknown';
+
+export interface BackupMeta {
+  reason: BackupReason;
+  label?: string; // Labelled backups are never pruned
+  created: string; // RFC 3339
+  checksum: string; // SHA-256 of the content, hex; also names the stored copy
+  source: string; // Hosts file it was taken from
+  app_version: string;
+  profile?: string; // Profile active at the time
+}
+
+export interface BackupInfo {
+  id: string; // YYYYMMDD_HHMMSS, with a counter for backups in the same second
   size: number;
+  stored_size: number; // Compressed, shared with backups of the same content
+  meta: BackupMeta;
+  error?: AppError; // Why the backup can't be restored, e.g. damaged data
+}
+
+// Which backups are kept; null turns a limit off
+export interface RetentionPolicy {
+  keep_last?: number | null; // Newest backups to keep (default 20)
+  keep_daily_days?: number | null; // Keep one backup per day for this many days (default 30)
+  max_total_bytes?: number | null; // Then drop the oldest beyond this size (default 50 MB)
 }
 
 // BETA-02: 중복/충돌 감지
@@ -64,3 +329,52 @@ export interface RemoteSource {
   lastUpdated?: string;
   lastStatus?: 'ok' | 'error';
 }
+
+// Hosts linter (lint_hosts)
+export type LintSeverity = 'info' | 'warning' | 'error';
+
+export interface LintFix {
+  description: string;
+  replacement: string | null; // null removes the line
+}
+
+export interface LintDiagnostic {
+  rule: string;
+  severity: LintSeverity;
+  line: number;
+  column: number;
+  message: string;
+  fix: LintFix | null;
+}
+
+export interface LintConfig {
+  rules?: Record<string, 'off' | LintSeverity>;
+  max_line_length?: number;
+  max_aliases?: number;
+}
+
+export type EntryErrorCode = 'invalid-ip' | 'missing-hostname' | 'invalid-hostname' | 'invalid-comment';
+
+export interface EntryError {
+  index: number; // Into HostsData.entries
+  id: string; // Empty for entries added in the UI
+  field: 'ip' | 'domains' | 'comment';
+  code: EntryErrorCode;
+  value: string;
+  message: string; // English description
+}
+
+// Hosts formatter (format_hosts)
+export interface FormatOptions {
+  align?: boolean;
+  layout?: 'preserve' | 'one-per-line' | 'group-by-ip';
+  lowercase?: boolean;
+  sort?: boolean;
+  collapse_duplicates?: boolean;
+}
+
+export interface Formatted {
+  text: string;
+  changed: boolean;
+  diff: string; // Unified diff against the input
+}
@@ -0,0 +1,36 @@
+import { invoke } from '@tauri-apps/api/core';
+import { HostsData, HostsDiff } from '../types';
+
+type Translate = (key: string, params?: Record<string, string | number>) => string;
+
+// How many changed entries a confirmation lists before it stops
+const SHOWN_CHANGES = 10;
+
+// Lines listing the entries a diff changes, for a confirm() prompt
+export function describeChanges(diff: HostsDiff, t: Translate): string {
+  if (diff.entries.length === 0) {
+    return t('preview.noChanges');
+  }
+  const shown = diff.entries
+    .slice(0, SHOWN_CHANGES)
+    .map(c => `  ${c.domain} (${c.kinds.join(', ')})`);
+  const more = diff.entries.length - shown.length;
+  return [
+    t('preview.changes', { count: diff.entries.length }),
+    ...shown,
+    ...(more > 0 ? [t('preview.more', { count: more })] : []),
+  ].join('\n');
+}
+
+// Ask before writing `data`, listing what it changes in the hosts file.
+// The question is asked without a list if the preview fails.
+export async function confirmChanges(data: HostsData, question: string, t: Translate): Promise<boolean> {
+  let summary = '';
+  try {
+    const diff = await invoke<HostsDiff>('preview_save', { data });
+    summary = '\n\n' + describeChanges(diff, t);
+  } catch (error) {
+    console.error('Failed to preview changes:', error);
+  }
+  return confirm(question + summary);
+}
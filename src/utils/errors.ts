@@ -0,0 +1,35 @@
+import { AppError, EntryError } from '../types';
+
+type Translate = (key: string, params?: Record<string, string | number>) => string;
+
+function isAppError(error: unknown): error is AppError {
+  return typeof error === 'object' && error !== null && 'code' in error && 'message' in error;
+}
+
+// Translation key for a kebab-case error code, e.g. errors.notFound
+function errorKey(code: string): string {
+  return 'errors.' + code.replace(/-(\w)/g, (_, c: string) => c.toUpperCase());
+}
+
+// Text to show for an error thrown by a backend command
+export function errorMessage(error: unknown, t: Translate): string {
+  if (!isAppError(error)) {
+    return error instanceof Error ? error.message : String(error);
+  }
+
+  const key = errorKey(error.code);
+  const text = t(key, {
+    path: error.path ?? '',
+    line: error.line ?? '',
+    message: error.message,
+  });
+  // Fall back to the English message for codes without a translation
+  return text === key ? error.message : text;
+}
+
+// Text to show for a problem with one entry, e.g. entryErrors.invalidIp
+export function entryErrorMessage(error: EntryError, t: Translate): string {
+  const key = 'entryErrors.' + error.code.replace(/-(\w)/g, (_, c: string) => c.toUpperCase());
+  const text = t(key, { value: error.value });
+  return text === key ? error.message : text;
+}